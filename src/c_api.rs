@@ -11,25 +11,85 @@
 // specific language governing permissions and limitations under
 // each license.
 
-use std::ffi::{c_char, c_int, c_long, CStr, CString};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_int, c_long, CStr, CString},
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+};
 
 use crate::{
-    C2paError, C2paSigner, ManifestBuilder, ManifestBuilderSettings, ManifestStoreReader, SeekMode,
-    SignerConfig, StreamAdapter, StreamError, StreamResult,
+    format_from_reader, C2paError, C2paErrorCode, C2paSigner, FileStream, IngredientBuilder,
+    IngredientBuilderSettings, ManifestBuilder, ManifestBuilderSettings, ManifestStoreReader,
+    ManifestStoreReaderSettings, MemoryStream, Relationship, Result, SeekMode, SignerConfig,
+    SpoolStream, StreamAdapter, StreamError, StreamResult, AUTO_FORMAT,
 };
 
+// The error reported by the last failing C callback on this thread,
+// with no code for the streams implemented in Rust
+thread_local! {
+    static CALLBACK_ERROR: RefCell<Option<(Option<c_int>, String)>> = const { RefCell::new(None) };
+    // the code of a failed callback, kept for the last error it causes
+    static CALLBACK_ERROR_CODE: Cell<Option<c_int>> = const { Cell::new(None) };
+}
+
+// Clears any error reported by an earlier callback
+fn clear_callback_error() {
+    CALLBACK_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+// Takes the error reported by a failing callback, or uses the default message
+fn take_callback_error(default: &str) -> String {
+    match CALLBACK_ERROR.with(|e| e.borrow_mut().take()) {
+        Some((Some(code), message)) => {
+            CALLBACK_ERROR_CODE.with(|c| c.set(Some(code)));
+            format!("{message} (code {code})")
+        }
+        Some((None, message)) => message,
+        None => default.to_string(),
+    }
+}
+
 /// Defines a callback to read from a stream
 type ReadCallback =
     unsafe extern "C" fn(context: *const StreamContext, data: *mut u8, len: usize) -> isize;
 
 /// Defines a callback to seek to an offset in a stream
+///
+/// Returns the new position in the stream, or a negative value on error.
+/// Streams are read in place, so the position must be correct.
+/// Offsets and positions are limited to c_long and c_int, use a Seek64Callback
+/// for streams over 2 GiB
 type SeekCallback =
     unsafe extern "C" fn(context: *const StreamContext, offset: c_long, mode: SeekMode) -> c_int;
 
+/// Defines a callback to seek to a 64 bit offset in a stream
+///
+/// Returns the new position in the stream, or a negative value on error.
+/// Streams are read in place, so the position must be correct
+type Seek64Callback =
+    unsafe extern "C" fn(context: *const StreamContext, offset: i64, mode: SeekMode) -> i64;
+
 /// Defines a callback to write to a stream
 type WriteCallback =
     unsafe extern "C" fn(context: *const StreamContext, data: *const u8, len: usize) -> isize;
 
+/// Defines a callback to flush any buffered writes of a stream
+///
+/// Returns 0 if successful, or a negative value on error
+type FlushCallback = unsafe extern "C" fn(context: *const StreamContext) -> c_int;
+
+/// Defines a callback to close a stream when it is released
+///
+/// Returns 0 if successful, or a negative value on error
+type CloseCallback = unsafe extern "C" fn(context: *const StreamContext) -> c_int;
+
+/// Defines a callback to get the length of a stream without seeking
+///
+/// Returns the length, or a negative value if it is not known,
+/// in which case the length is found by seeking to the end
+type LengthCallback = unsafe extern "C" fn(context: *const StreamContext) -> i64;
+
 /// Defines a callback to sign data
 type SignerCallback = unsafe extern "C" fn(
     data: *mut u8,
@@ -38,6 +98,45 @@ type SignerCallback = unsafe extern "C" fn(
     sig_max_size: isize,
 ) -> isize;
 
+/// Defines a callback to sign data with a context
+///
+/// Returns the size of the signature, or a negative value on error
+type SignerContextCallback = unsafe extern "C" fn(
+    context: *const SignerContext,
+    data: *const u8,
+    len: usize,
+    signature: *mut u8,
+    sig_max_size: usize,
+) -> isize;
+
+/// Defines a callback to release the context of a signer
+type SignerContextRelease = unsafe extern "C" fn(context: *const SignerContext);
+
+/// Defines a callback to fetch a remote manifest store
+///
+/// Returns the size of the manifest store, or a negative value on error.
+/// If the size is larger than len, the callback is called again with a large enough buffer
+type FetchCallback = unsafe extern "C" fn(
+    context: *const FetchContext,
+    url: *const c_char,
+    data: *mut u8,
+    len: usize,
+) -> isize;
+
+#[repr(C)]
+#[derive(Debug)]
+/// An Opaque struct to hold a context value for the signer callback
+pub struct SignerContext {
+    _priv: (),
+}
+
+#[repr(C)]
+#[derive(Debug)]
+/// An Opaque struct to hold a context value for the fetch callback
+pub struct FetchContext {
+    _priv: (),
+}
+
 #[repr(C)]
 #[derive(Debug)]
 /// An Opaque struct to hold a context value for the stream callbacks
@@ -45,11 +144,30 @@ pub struct StreamContext {
     _priv: (),
 }
 
+/// Configuration settings for the ManifestStoreReader
+#[repr(C)]
+pub struct ManifestStoreReaderSettingsC {
+    /// The maximum size in bytes of a stream that will be read, 0 for no limit
+    pub max_stream_size: u64,
+}
+
 /// Configuration settings for the ManifestBuilder
 /// this is mostly a placeholder for future expansion
 #[repr(C)]
 pub struct ManifestBuilderSettingsC {
     pub claim_generator: *const c_char,
+    /// If set, signing writes a manifest store (.c2pa) to the output
+    /// and leaves the asset unmodified
+    pub sidecar: bool,
+}
+
+/// Configuration settings for the IngredientBuilder
+#[repr(C)]
+pub struct IngredientBuilderSettingsC {
+    pub title: *const c_char,
+    pub format: *const c_char,
+    pub instance_id: *const c_char,
+    pub relationship: Relationship,
 }
 
 #[repr(C)]
@@ -112,6 +230,7 @@ impl crate::SignerCallback for CSignerCallback {
 
         // This callback returns the size of the signature, if negative it means there was an error
         let sig: *mut u8 = signature.as_ptr() as *mut u8;
+        clear_callback_error();
         let result = unsafe {
             (self.signer)(
                 data.as_ptr() as *mut u8,
@@ -121,9 +240,13 @@ impl crate::SignerCallback for CSignerCallback {
             )
         };
         if result < 0 {
-            // todo: return errors from callback
             return Err(StreamError::Other {
-                reason: "signer error".to_string(),
+                reason: take_callback_error("signer error"),
+            });
+        }
+        if result as usize > sig_max_size {
+            return Err(StreamError::Other {
+                reason: format!("signer returned {result} bytes, more than {sig_max_size}"),
             });
         }
         signature.truncate(result as usize);
@@ -132,29 +255,220 @@ impl crate::SignerCallback for CSignerCallback {
     }
 }
 
+/// A signer in C that is passed a context on every call
+struct CSignerContextCallback {
+    context: *const SignerContext,
+    signer: SignerContextCallback,
+    release: Option<SignerContextRelease>,
+}
+
+// The context is owned by the caller, who must ensure it can be used from any thread
+unsafe impl Send for CSignerContextCallback {}
+unsafe impl Sync for CSignerContextCallback {}
+
+impl crate::SignerCallback for CSignerContextCallback {
+    fn sign(&self, data: Vec<u8>) -> StreamResult<Vec<u8>> {
+        // the signature buffer is preallocated to the maximum size, as for CSignerCallback
+        let sig_max_size = 100000;
+        let mut signature = vec![0; sig_max_size];
+        clear_callback_error();
+        let result = unsafe {
+            (self.signer)(
+                self.context,
+                data.as_ptr(),
+                data.len(),
+                signature.as_mut_ptr(),
+                sig_max_size,
+            )
+        };
+        if result < 0 {
+            return Err(StreamError::Other {
+                reason: take_callback_error("signer error"),
+            });
+        }
+        // a size beyond the buffer means the callback overran it
+        if result as usize > sig_max_size {
+            return Err(StreamError::Other {
+                reason: format!("signer returned {result} bytes, more than {sig_max_size}"),
+            });
+        }
+        signature.truncate(result as usize);
+        Ok(signature)
+    }
+}
+
+impl Drop for CSignerContextCallback {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self.context) };
+        }
+    }
+}
+
+// Internal routine to convert a SignerConfigC to a SignerConfig
+unsafe fn signer_config_from_c(config: *const SignerConfigC) -> Result<SignerConfig> {
+    let config = deref(config, "config")?;
+    Ok(SignerConfig {
+        alg: from_c_str(config.alg, "alg")?.to_lowercase(),
+        certs: from_c_str(config.certs, "certs")?.into_bytes(),
+        time_authority_url: from_opt_c_str(config.time_authority_url, "time_authority_url")?,
+        use_ocsp: config.use_ocsp,
+    })
+}
+
+// Internal routine to configure a signer and return it to C
+unsafe fn configured_signer(
+    signer: C2paSigner,
+    config: *const SignerConfigC,
+) -> Result<*mut C2paSigner> {
+    signer.configure(&signer_config_from_c(config)?)?;
+    Ok(Box::into_raw(Box::new(signer)))
+}
+
+/// Creates a signer that calls a signing callback
+///
+/// # Arguments
+/// * `signer` - the callback that signs data, returning the signature size or a negative value on error
+/// * `config` - a pointer to a SignerConfigC
+///
+/// # Returns
+/// * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The returned value MUST be released by calling c2pa_release_signer
+/// The signer can be used by any number of builders and threads until it is released
+///
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_create_signer(
-    signer: SignerCallback,
-    config: &SignerConfigC,
+    signer: Option<SignerCallback>,
+    config: *const SignerConfigC,
 ) -> *mut C2paSigner {
-    let config = SignerConfig {
-        alg: from_c_str(config.alg).to_lowercase(),
-        certs: from_c_str(config.certs).into_bytes(),
-        time_authority_url: if config.time_authority_url.is_null() {
-            None
-        } else {
-            Some(from_c_str(config.time_authority_url))
-        },
-        use_ocsp: config.use_ocsp,
-    };
-    let callback = Box::new(CSignerCallback { signer });
-    let signer = C2paSigner::new(callback);
-    match signer.configure(&config) {
-        Ok(_) => Box::into_raw(Box::new(signer)),
-        Err(e) => {
-            e.set_last();
-            std::ptr::null_mut()
+    catch_panic(std::ptr::null_mut(), || {
+        let signer = callback(signer, "signer")?;
+        let callback = Box::new(CSignerCallback { signer });
+        configured_signer(C2paSigner::new(callback), config)
+    })
+}
+
+/// Creates a signer with a context that is passed to every call of the callback
+///
+/// # Arguments
+/// * `context` - an opaque pointer to the state of the signer, such as a key handle
+/// * `signer` - the callback that signs data, returning the signature size or a negative value on error
+/// * `release` - an optional callback to release the context when the signer is dropped, may be NULL
+/// * `config` - a pointer to a SignerConfigC
+///
+/// # Returns
+/// * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The context must remain valid, and usable from any thread, for the lifetime of the signer
+/// If the signer cannot be created the release callback is called before returning
+/// The returned value MUST be released by calling c2pa_release_signer,
+/// which then calls the release callback
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_signer_with_context(
+    context: *const SignerContext,
+    signer: Option<SignerContextCallback>,
+    release: Option<SignerContextRelease>,
+    config: *const SignerConfigC,
+) -> *mut C2paSigner {
+    catch_panic(std::ptr::null_mut(), || {
+        let Some(signer) = signer else {
+            if let Some(release) = release {
+                release(context);
+            }
+            return Err(C2paError::NullParameter("signer".to_string()));
+        };
+        let callback = Box::new(CSignerContextCallback {
+            context,
+            signer,
+            release,
+        });
+        configured_signer(C2paSigner::new(callback), config)
+    })
+}
+
+/// Creates a signer that signs with a private key, without a callback
+///
+/// # Arguments
+/// * `alg` - the signing algorithm, one of es256, es384, es512, ps256, ps384, ps512 or ed25519
+/// * `certs` - the PEM encoded certificate chain, starting with the signing certificate
+/// * `private_key` - the PEM encoded private key
+/// * `tsa_url` - an optional url of a time stamp authority, may be NULL
+///
+/// # Returns
+/// * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The returned value MUST be released by calling c2pa_release_signer
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_signer_from_keys(
+    alg: *const c_char,
+    certs: *const c_char,
+    private_key: *const c_char,
+    tsa_url: *const c_char,
+) -> *mut C2paSigner {
+    catch_panic(std::ptr::null_mut(), || {
+        let signer = C2paSigner::from_keys(
+            &from_c_str(alg, "alg")?,
+            &from_c_str(certs, "certs")?,
+            &from_c_str(private_key, "private_key")?,
+            from_opt_c_str(tsa_url, "tsa_url")?,
+        )?;
+        Ok(Box::into_raw(Box::new(signer)))
+    })
+}
+
+/// A ManifestFetcher that calls a fetch callback in C
+struct CManifestFetcher {
+    context: *const FetchContext,
+    fetch: FetchCallback,
+}
+
+// The context is owned by the caller, who must ensure it can be used from any thread
+unsafe impl Send for CManifestFetcher {}
+unsafe impl Sync for CManifestFetcher {}
+
+impl CManifestFetcher {
+    // The initial buffer size, large enough for most manifest stores
+    const BUFFER_SIZE: usize = 0x10000;
+
+    fn fetch_into(&self, url: &CStr, buf: &mut [u8]) -> StreamResult<usize> {
+        clear_callback_error();
+        let len = unsafe { (self.fetch)(self.context, url.as_ptr(), buf.as_mut_ptr(), buf.len()) };
+        if len < 0 {
+            return Err(StreamError::Other {
+                reason: take_callback_error(&format!("failed to fetch {}", url.to_string_lossy())),
+            });
+        }
+        Ok(len as usize)
+    }
+}
+
+impl crate::ManifestFetcher for CManifestFetcher {
+    fn fetch(&self, url: String) -> StreamResult<Vec<u8>> {
+        let url = CString::new(url).map_err(|e| StreamError::Other {
+            reason: e.to_string(),
+        })?;
+        let mut buf = vec![0; Self::BUFFER_SIZE];
+        let mut len = self.fetch_into(&url, &mut buf)?;
+        if len > buf.len() {
+            buf.resize(len, 0);
+            len = self.fetch_into(&url, &mut buf)?;
+            if len > buf.len() {
+                return Err(StreamError::Other {
+                    reason: "manifest store size changed while fetching".to_string(),
+                });
+            }
         }
+        buf.truncate(len);
+        Ok(buf)
     }
 }
 
@@ -164,8 +478,13 @@ pub unsafe extern "C" fn c2pa_create_signer(
 pub struct C2paStream {
     context: Box<StreamContext>,
     read_callback: ReadCallback,
-    seek_callback: SeekCallback,
+    seek_callback: Option<SeekCallback>,
     write_callback: WriteCallback,
+    flush_callback: Option<FlushCallback>,
+    close_callback: Option<CloseCallback>,
+    length_callback: Option<LengthCallback>,
+    // used instead of seek_callback when set
+    seek64_callback: Option<Seek64Callback>,
 }
 
 impl C2paStream {
@@ -188,8 +507,46 @@ impl C2paStream {
         Self {
             context: unsafe { Box::from_raw(context) },
             read_callback,
-            seek_callback,
+            seek_callback: Some(seek_callback),
+            write_callback,
+            flush_callback: None,
+            close_callback: None,
+            length_callback: None,
+            seek64_callback: None,
+        }
+    }
+
+    /// Creates a new C2paStream from context with callbacks, seeking with 64 bit offsets
+    /// # Safety
+    ///     The same as for C2paStream::new
+    pub unsafe fn with_seek64(
+        context: *mut StreamContext,
+        read_callback: ReadCallback,
+        seek_callback: Seek64Callback,
+        write_callback: WriteCallback,
+    ) -> Self {
+        Self {
+            context: unsafe { Box::from_raw(context) },
+            read_callback,
+            seek_callback: None,
             write_callback,
+            flush_callback: None,
+            close_callback: None,
+            length_callback: None,
+            seek64_callback: Some(seek_callback),
+        }
+    }
+
+    unsafe fn seek(&self, pos: i64, mode: SeekMode) -> StreamResult<i64> {
+        match (self.seek64_callback, self.seek_callback) {
+            (Some(seek), _) => Ok(seek(&(*self.context), pos, mode)),
+            (None, Some(seek)) => {
+                let offset = c_long::try_from(pos).map_err(|_| StreamError::Other {
+                    reason: format!("seek offset {pos} is too large for a SeekCallback"),
+                })?;
+                Ok(seek(&(*self.context), offset, mode) as i64)
+            }
+            (None, None) => Err(StreamError::InternalStreamError),
         }
     }
 }
@@ -197,25 +554,159 @@ impl C2paStream {
 impl crate::Stream for C2paStream {
     fn read_stream(&self, len: u64) -> StreamResult<Vec<u8>> {
         let mut buf = vec![0; len as usize];
+        clear_callback_error();
         let bytes_read =
             unsafe { (self.read_callback)(&(*self.context), buf.as_mut_ptr(), buf.len()) };
+        if bytes_read < 0 {
+            return Err(StreamError::Io {
+                reason: take_callback_error("stream read failed"),
+            });
+        }
         buf.truncate(bytes_read as usize);
         Ok(buf)
     }
     fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
-        let new_pos = unsafe { (self.seek_callback)(&(*self.context), pos as c_long, mode) };
+        clear_callback_error();
+        let new_pos = unsafe { self.seek(pos, mode)? };
+        if new_pos < 0 {
+            return Err(StreamError::Io {
+                reason: take_callback_error("stream seek failed"),
+            });
+        }
         Ok(new_pos as u64)
     }
     fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        clear_callback_error();
         let bytes_written =
             unsafe { (self.write_callback)(&(*self.context), data.as_ptr(), data.len()) };
+        if bytes_written < 0 {
+            return Err(StreamError::Io {
+                reason: take_callback_error("stream write failed"),
+            });
+        }
         Ok(bytes_written as u64)
     }
+    fn flush_stream(&self) -> StreamResult<()> {
+        if let Some(flush) = self.flush_callback {
+            clear_callback_error();
+            if unsafe { flush(&(*self.context)) } < 0 {
+                return Err(StreamError::Io {
+                    reason: take_callback_error("stream flush failed"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        Ok(self
+            .length_callback
+            .and_then(|length| u64::try_from(unsafe { length(&(*self.context)) }).ok()))
+    }
+}
+
+impl Drop for C2paStream {
+    fn drop(&mut self) {
+        if let Some(close) = self.close_callback {
+            clear_callback_error();
+            if unsafe { close(&(*self.context)) } < 0 {
+                C2paError::Io(take_callback_error("stream close failed"))
+                    .set_last_with_callback_code(CALLBACK_ERROR_CODE.with(Cell::take));
+            }
+        }
+    }
+}
+
+// Internal routine to run the body of an extern "C" function so that panics are not
+// unwound into C. An error or panic is set as the last error and the failure value returned
+fn catch_panic<T>(failure: T, f: impl FnOnce() -> Result<T>) -> T {
+    let result = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = if let Some(s) = payload.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = payload.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_string()
+            };
+            Err(C2paError::Panic(message))
+        }
+    };
+    // a callback failure only belongs to the error of the call it happened in
+    let callback_code = CALLBACK_ERROR_CODE.with(Cell::take);
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            e.set_last_with_callback_code(callback_code);
+            failure
+        }
+    }
+}
+
+// Internal routine to check a pointer argument, returning a reference to what it points to
+unsafe fn deref<'a, T>(ptr: *const T, name: &str) -> Result<&'a T> {
+    ptr.as_ref()
+        .ok_or_else(|| C2paError::NullParameter(name.to_string()))
+}
+
+// Internal routine to check a mutable pointer argument
+unsafe fn deref_mut<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut T> {
+    ptr.as_mut()
+        .ok_or_else(|| C2paError::NullParameter(name.to_string()))
+}
+
+// Internal routine to check a pointer to a pointer to an object allocated by Rust
+unsafe fn deref_ptr<'a, T>(ptr: *mut *mut T, name: &str) -> Result<&'a T> {
+    deref(*deref(ptr, name)?, name)
+}
+
+// Internal routine to check a pointer to a buffer of len bytes
+unsafe fn buffer<'a>(ptr: *const u8, len: usize, name: &str) -> Result<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(C2paError::NullParameter(name.to_string()));
+    }
+    Ok(std::slice::from_raw_parts(ptr, len))
+}
+
+// Internal routine to check a pointer to a mutable buffer of len bytes
+unsafe fn buffer_mut<'a>(ptr: *mut u8, len: usize, name: &str) -> Result<&'a mut [u8]> {
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    if ptr.is_null() {
+        return Err(C2paError::NullParameter(name.to_string()));
+    }
+    Ok(std::slice::from_raw_parts_mut(ptr, len))
+}
+
+// Internal routine to check a callback argument
+fn callback<T>(callback: Option<T>, name: &str) -> Result<T> {
+    callback.ok_or_else(|| C2paError::NullParameter(name.to_string()))
 }
 
 // Internal routine to convert a *const c_char to a rust String
-unsafe fn from_c_str(s: *const c_char) -> String {
-    CStr::from_ptr(s).to_string_lossy().into_owned()
+// A null pointer or invalid UTF-8 is reported as an error for the named argument
+unsafe fn from_c_str(s: *const c_char, name: &str) -> Result<String> {
+    if s.is_null() {
+        return Err(C2paError::NullParameter(name.to_string()));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(str::to_string)
+        .map_err(|e| C2paError::Decoding(format!("{name} is not valid UTF-8: {e}")))
+}
+
+// Internal routine to convert an optional *const c_char to a rust String
+unsafe fn from_opt_c_str(s: *const c_char, name: &str) -> Result<Option<String>> {
+    if s.is_null() {
+        Ok(None)
+    } else {
+        from_c_str(s, name).map(Some)
+    }
 }
 
 // Internal routine to return a rust String reference to C as *mut c_char
@@ -238,7 +729,61 @@ unsafe fn to_c_string(s: String) -> *mut c_char {
 /// and it is no longer valid after that call.
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_error() -> *mut c_char {
-    to_c_string(C2paError::last_message().unwrap_or_default())
+    catch_panic(std::ptr::null_mut(), || {
+        Ok(to_c_string(C2paError::last_message().unwrap_or_default()))
+    })
+}
+
+/// Returns the code of the last error
+///
+/// The codes are stable and match the variants of the error enum,
+/// C2paErrorCode_None is returned if there is no error
+#[no_mangle]
+pub extern "C" fn c2pa_error_code() -> C2paErrorCode {
+    catch_panic(C2paErrorCode::Panic, || Ok(C2paError::last_code()))
+}
+
+/// Returns a json description of the last error
+///
+/// This holds the code, variant and message of the error,
+/// along with any c2pa validation codes that describe it,
+/// and the code passed to c2pa_set_callback_error by the callback that caused it, or null
+///
+/// # Safety
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+/// Returns NULL if there is no error
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_error_details_json() -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        Ok(match C2paError::last_details_json() {
+            Some(json) => to_c_string(json),
+            None => std::ptr::null_mut(),
+        })
+    })
+}
+
+/// Reports the cause of a failure from a C callback
+///
+/// A stream, signer or fetch callback calls this before returning a negative value,
+/// and the message is then included in the error returned by c2pa_error.
+/// The code is reported as callback_code by c2pa_error_details_json
+///
+/// # Arguments
+/// * `code` - an error code defined by the caller, such as an errno value
+/// * `message` - a null terminated description of the error, may be NULL
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_set_callback_error(code: c_int, message: *const c_char) {
+    catch_panic((), || {
+        let message =
+            from_opt_c_str(message, "message")?.unwrap_or_else(|| "callback error".to_string());
+        CALLBACK_ERROR.with(|e| *e.borrow_mut() = Some((Some(code), message)));
+        Ok(())
+    })
 }
 
 /// Returns a version string for logging
@@ -248,7 +793,7 @@ pub unsafe extern "C" fn c2pa_error() -> *mut c_char {
 /// and it is no longer valid after that call.
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_version() -> *mut c_char {
-    to_c_string(crate::version())
+    catch_panic(std::ptr::null_mut(), || Ok(to_c_string(crate::version())))
 }
 
 /// Returns a JSON array of supported file format extensions
@@ -258,7 +803,11 @@ pub unsafe extern "C" fn c2pa_version() -> *mut c_char {
 /// and it is no longer valid after that call.
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_supported_extensions() -> *mut c_char {
-    to_c_string(serde_json::to_string(&crate::supported_extensions()).unwrap_or_default())
+    catch_panic(std::ptr::null_mut(), || {
+        let extensions = serde_json::to_string(&crate::supported_extensions())
+            .map_err(|e| C2paError::Json(e.to_string()))?;
+        Ok(to_c_string(extensions))
+    })
 }
 
 /// Creates a new C2paStream from context with callbacks
@@ -278,68 +827,635 @@ pub unsafe extern "C" fn c2pa_supported_extensions() -> *mut c_char {
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_create_stream(
     context: *mut StreamContext,
-    read: ReadCallback,
-    seek: SeekCallback,
-    write: WriteCallback,
+    read: Option<ReadCallback>,
+    seek: Option<SeekCallback>,
+    write: Option<WriteCallback>,
 ) -> *mut C2paStream {
-    Box::into_raw(Box::new(C2paStream::new(context, read, seek, write)))
+    catch_panic(std::ptr::null_mut(), || {
+        if context.is_null() {
+            return Err(C2paError::NullParameter("context".to_string()));
+        }
+        let (read, seek, write) = (
+            callback(read, "read")?,
+            callback(seek, "seek")?,
+            callback(write, "write")?,
+        );
+        Ok(Box::into_raw(Box::new(C2paStream::new(
+            context, read, seek, write,
+        ))))
+    })
 }
 
-/// Verify a stream and return a ManifestStore report
+/// Creates a new C2paStream from context with callbacks, including flush and close
 ///
-/// # Errors
-/// Returns an error field if there were errors
+/// # Arguments
+/// * `context` - a pointer to a StreamContext
+/// * `read` - a ReadCallback to read from the stream
+/// * `seek` - a SeekCallback to seek in the stream
+/// * `write` - a WriteCallback to write to the stream
+/// * `flush` - an optional FlushCallback, called when signing has written the output, may be NULL
+/// * `close` - an optional CloseCallback, called when the stream is released, may be NULL
 ///
 /// # Safety
-/// The returned value MUST be released by calling release_string
-/// and it is no longer valid after that call.
+/// The context must remain valid for the lifetime of the C2paStream
+/// The resulting C2paStream must be released by calling c2pa_release_stream,
+/// a close error is then available from c2pa_error
+///
 #[no_mangle]
-pub unsafe extern "C" fn c2pa_verify_stream(reader: &mut C2paStream) -> *mut c_char {
-    let manifest_store = ManifestStoreReader::new();
-    let mut reader = StreamAdapter::from_stream_mut(reader);
-    let result = manifest_store.read("image/jpeg", &mut reader);
-    let str = match result {
-        Ok(json) => json,
-        Err(e) => {
-            e.set_last();
-            return std::ptr::null_mut();
-        }
-    };
-    to_c_string(str)
+pub unsafe extern "C" fn c2pa_create_stream_ex(
+    context: *mut StreamContext,
+    read: Option<ReadCallback>,
+    seek: Option<SeekCallback>,
+    write: Option<WriteCallback>,
+    flush: Option<FlushCallback>,
+    close: Option<CloseCallback>,
+) -> *mut C2paStream {
+    let stream = c2pa_create_stream(context, read, seek, write);
+    if let Some(stream) = stream.as_mut() {
+        stream.flush_callback = flush;
+        stream.close_callback = close;
+    }
+    stream
 }
 
-/// Create a new ManifestStoreReader
-///
-/// # Safety
-/// The returned value MUST be released by calling release_manifest_reader
+/// The callbacks of a C2paStream created by c2pa_create_stream_with_callbacks
 ///
-/// # Example
-/// ```
-/// use c2pa::ManifestStoreReader;
-/// let reader = ManifestStoreReader::new();
-/// ```
-#[no_mangle]
-pub unsafe extern "C" fn c2pa_manifest_reader_new() -> *mut ManifestStoreReader {
-    let reader = ManifestStoreReader::new();
-    Box::into_raw(Box::new(reader))
+/// The read, seek and write callbacks are required, the others may be NULL
+#[repr(C)]
+pub struct C2paStreamCallbacks {
+    /// Reads from the stream
+    pub read: Option<ReadCallback>,
+    /// Seeks in the stream with 64 bit offsets
+    pub seek: Option<Seek64Callback>,
+    /// Writes to the stream
+    pub write: Option<WriteCallback>,
+    /// Called when signing has written the output
+    pub flush: Option<FlushCallback>,
+    /// Called when the stream is released
+    pub close: Option<CloseCallback>,
+    /// Used instead of seeking to the end to find the length of the stream
+    pub length: Option<LengthCallback>,
 }
 
-/// Read a manifest store from a stream
+/// Creates a new C2paStream from context with a set of callbacks
+///
+/// Unlike c2pa_create_stream, the stream seeks with 64 bit offsets,
+/// so it can be used for streams over 2 GiB
 ///
 /// # Arguments
-/// * `reader_ptr` - a pointer to a ManifestStoreReader
-/// * `format` - the format of the manifest store
-/// * `stream` - the stream to read from
+/// * `context` - a pointer to a StreamContext
+/// * `callbacks` - the callbacks of the stream, which are copied
 ///
 /// # Returns
-/// * `Result<String>` - the json representation of the manifest store
+/// * a pointer to a C2paStream, or NULL with the error available from c2pa_error
 ///
-/// # Example
-/// ```
-/// use c2pa::ManifestStoreReader;
-/// use std::io::Cursor;
-///     
-/// let reader = ManifestStoreReader::new();
+/// # Safety
+/// The context must remain valid for the lifetime of the C2paStream
+/// The resulting C2paStream must be released by calling c2pa_release_stream,
+/// a close error is then available from c2pa_error
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_stream_with_callbacks(
+    context: *mut StreamContext,
+    callbacks: *const C2paStreamCallbacks,
+) -> *mut C2paStream {
+    catch_panic(std::ptr::null_mut(), || {
+        let callbacks = deref(callbacks, "callbacks")?;
+        if context.is_null() {
+            return Err(C2paError::NullParameter("context".to_string()));
+        }
+        let mut stream = C2paStream::with_seek64(
+            context,
+            callback(callbacks.read, "read")?,
+            callback(callbacks.seek, "seek")?,
+            callback(callbacks.write, "write")?,
+        );
+        stream.flush_callback = callbacks.flush;
+        stream.close_callback = callbacks.close;
+        stream.length_callback = callbacks.length;
+        Ok(Box::into_raw(Box::new(stream)))
+    })
+}
+
+/// Sets the callback used to find the length of a stream
+///
+/// # Arguments
+/// * `stream` - a pointer to a C2paStream
+/// * `length` - a LengthCallback, used instead of seeking to the end
+///   to find the length of the stream, or NULL to always seek
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// The stream must be a valid C2paStream, created by one of the c2pa_create_stream functions
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_stream_set_length_callback(
+    stream: *mut C2paStream,
+    length: Option<LengthCallback>,
+) -> c_int {
+    catch_panic(-1, || {
+        deref_mut(stream, "stream")?.length_callback = length;
+        Ok(0)
+    })
+}
+
+// The read and seek callbacks of a write-only C2paStream, which always fail
+unsafe extern "C" fn write_only_read(
+    _context: *const StreamContext,
+    _data: *mut u8,
+    _len: usize,
+) -> isize {
+    native_stream_error(StreamError::Other {
+        reason: "cannot read from a write-only stream".to_string(),
+    }) as isize
+}
+
+unsafe extern "C" fn write_only_seek(
+    _context: *const StreamContext,
+    _offset: i64,
+    _mode: SeekMode,
+) -> i64 {
+    native_stream_error(StreamError::Other {
+        reason: "cannot seek in a write-only stream".to_string(),
+    }) as i64
+}
+
+// The seek and write callbacks of the forward-only source of a spool stream, which always fail
+unsafe extern "C" fn forward_only_seek(
+    _context: *const StreamContext,
+    _offset: i64,
+    _mode: SeekMode,
+) -> i64 {
+    native_stream_error(StreamError::Other {
+        reason: "cannot seek in a forward-only stream".to_string(),
+    }) as i64
+}
+
+unsafe extern "C" fn forward_only_write(
+    _context: *const StreamContext,
+    _data: *const u8,
+    _len: usize,
+) -> isize {
+    native_stream_error(StreamError::Other {
+        reason: "cannot write to a forward-only stream".to_string(),
+    }) as isize
+}
+
+/// Creates a new write-only C2paStream from context with callbacks
+///
+/// This allows signing into a pipe, socket or response body with
+/// c2pa_manifest_builder_sign_sequential, which only writes and flushes the output
+///
+/// # Arguments
+/// * `context` - a pointer to a StreamContext
+/// * `write` - a WriteCallback to write to the stream
+/// * `flush` - an optional FlushCallback, called when signing has written the output, may be NULL
+/// * `close` - an optional CloseCallback, called when the stream is released, may be NULL
+///
+/// # Safety
+/// The context must remain valid for the lifetime of the C2paStream
+/// The resulting C2paStream must be released by calling c2pa_release_stream
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_write_stream(
+    context: *mut StreamContext,
+    write: Option<WriteCallback>,
+    flush: Option<FlushCallback>,
+    close: Option<CloseCallback>,
+) -> *mut C2paStream {
+    c2pa_create_stream_with_callbacks(
+        context,
+        &C2paStreamCallbacks {
+            read: Some(write_only_read),
+            seek: Some(write_only_seek),
+            write,
+            flush,
+            close,
+            length: None,
+        },
+    )
+}
+
+// Reports a failed native stream operation as a callback error
+fn native_stream_error(e: StreamError) -> c_int {
+    CALLBACK_ERROR.with(|c| *c.borrow_mut() = Some((None, e.to_string())));
+    -1
+}
+
+// A Stream implemented in Rust that can be the context of a C2paStream
+trait RustStreamContext: crate::Stream {
+    fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize>;
+    fn write_from(&self, buf: &[u8]) -> StreamResult<usize>;
+}
+
+impl RustStreamContext for FileStream {
+    fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        FileStream::read_into(self, buf)
+    }
+
+    fn write_from(&self, buf: &[u8]) -> StreamResult<usize> {
+        FileStream::write_from(self, buf)
+    }
+}
+
+impl RustStreamContext for SpoolStream {
+    fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        SpoolStream::read_into(self, buf)
+    }
+
+    fn write_from(&self, buf: &[u8]) -> StreamResult<usize> {
+        SpoolStream::write_from(self, buf)
+    }
+}
+
+impl RustStreamContext for MemoryStream {
+    fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        MemoryStream::read_into(self, buf)
+    }
+
+    fn write_from(&self, buf: &[u8]) -> StreamResult<usize> {
+        MemoryStream::write_from(self, buf)
+    }
+}
+
+// The callbacks of a C2paStream over a NativeStream, the context is the NativeStream
+unsafe extern "C" fn native_stream_read<S: RustStreamContext>(
+    context: *const StreamContext,
+    data: *mut u8,
+    len: usize,
+) -> isize {
+    let stream = &*(context as *const S);
+    match stream.read_into(std::slice::from_raw_parts_mut(data, len)) {
+        Ok(len) => len as isize,
+        Err(e) => native_stream_error(e) as isize,
+    }
+}
+
+unsafe extern "C" fn native_stream_seek<S: RustStreamContext>(
+    context: *const StreamContext,
+    offset: i64,
+    mode: SeekMode,
+) -> i64 {
+    let stream = &*(context as *const S);
+    let pos = stream.seek_stream(offset, mode).and_then(|pos| {
+        i64::try_from(pos).map_err(|_| StreamError::Other {
+            reason: format!("stream position {pos} is out of range"),
+        })
+    });
+    match pos {
+        Ok(pos) => pos,
+        Err(e) => native_stream_error(e) as i64,
+    }
+}
+
+unsafe extern "C" fn native_stream_write<S: RustStreamContext>(
+    context: *const StreamContext,
+    data: *const u8,
+    len: usize,
+) -> isize {
+    let stream = &*(context as *const S);
+    match stream.write_from(std::slice::from_raw_parts(data, len)) {
+        Ok(len) => len as isize,
+        Err(e) => native_stream_error(e) as isize,
+    }
+}
+
+unsafe extern "C" fn native_stream_flush<S: RustStreamContext>(
+    context: *const StreamContext,
+) -> c_int {
+    let stream = &*(context as *const S);
+    match stream.flush_stream() {
+        Ok(_) => 0,
+        Err(e) => native_stream_error(e),
+    }
+}
+
+unsafe extern "C" fn native_stream_length<S: RustStreamContext>(
+    context: *const StreamContext,
+) -> i64 {
+    let stream = &*(context as *const S);
+    match stream.stream_length() {
+        Ok(Some(len)) => i64::try_from(len).unwrap_or(-1),
+        _ => -1,
+    }
+}
+
+unsafe extern "C" fn native_stream_close<S: RustStreamContext>(
+    context: *const StreamContext,
+) -> c_int {
+    drop(Box::from_raw(context as *mut S));
+    0
+}
+
+// Creates a C2paStream that owns a NativeStream, released by the close callback
+unsafe fn native_c2pa_stream<S: RustStreamContext>(stream: S) -> C2paStream {
+    let context = Box::into_raw(Box::new(stream)) as *mut StreamContext;
+    let mut stream = C2paStream::with_seek64(
+        context,
+        native_stream_read::<S>,
+        native_stream_seek::<S>,
+        native_stream_write::<S>,
+    );
+    stream.flush_callback = Some(native_stream_flush::<S>);
+    stream.close_callback = Some(native_stream_close::<S>);
+    stream.length_callback = Some(native_stream_length::<S>);
+    stream
+}
+
+unsafe fn native_stream<S: RustStreamContext>(stream: S) -> *mut C2paStream {
+    Box::into_raw(Box::new(native_c2pa_stream(stream)))
+}
+
+/// A memory stream for C, whose contents can be read back after signing
+///
+/// This is a separate handle from C2paStream, so that the contents can only be
+/// read from a stream that is known to be a memory stream
+pub struct C2paMemoryStream {
+    stream: C2paStream,
+}
+
+impl C2paMemoryStream {
+    fn memory(&self) -> &MemoryStream {
+        // the context of the stream is the MemoryStream it was created with
+        unsafe { &*(&*self.stream.context as *const StreamContext as *const MemoryStream) }
+    }
+}
+
+/// Opens a C2paStream over a file, implemented in Rust
+///
+/// The stream reads and writes the file directly, without any C callbacks
+///
+/// # Arguments
+/// * `path` - the path of the file
+/// * `mode` - an fopen style mode: "r", "w", "a", optionally with "+" and "b"
+///
+/// # Returns
+/// * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The resulting C2paStream must be released by calling c2pa_release_stream,
+/// which closes the file
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_open_file_stream(
+    path: *const c_char,
+    mode: *const c_char,
+) -> *mut C2paStream {
+    catch_panic(std::ptr::null_mut(), || {
+        let path = from_c_str(path, "path")?;
+        let mode = from_c_str(mode, "mode")?;
+        let stream = FileStream::new(&path, &mode).map_err(|e| C2paError::Io(e.to_string()))?;
+        Ok(native_stream(stream))
+    })
+}
+
+/// Creates a memory stream, implemented in Rust
+///
+/// The stream starts with a copy of the data and is positioned at the start.
+/// It is used as a C2paStream through c2pa_memory_stream_as_stream,
+/// so it can be written to by signing and its contents read back with c2pa_memory_stream_data
+///
+/// # Arguments
+/// * `data` - the initial contents of the stream, may be NULL if len is 0
+/// * `len` - the size of the data
+///
+/// # Returns
+/// * a pointer to a C2paMemoryStream, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// The data must be valid for len bytes
+/// The resulting C2paMemoryStream must be released by calling c2pa_release_memory_stream
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_memory_stream(
+    data: *const u8,
+    len: usize,
+) -> *mut C2paMemoryStream {
+    catch_panic(std::ptr::null_mut(), || {
+        let data = buffer(data, len, "data")?.to_vec();
+        Ok(Box::into_raw(Box::new(C2paMemoryStream {
+            stream: native_c2pa_stream(MemoryStream::from_bytes(data)),
+        })))
+    })
+}
+
+/// Returns the C2paStream of a memory stream, to read, sign or write to
+///
+/// # Returns
+/// * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// The C2paStream belongs to the memory stream, it is only valid until
+/// the memory stream is released and must not be released itself
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_memory_stream_as_stream(
+    memory: *mut C2paMemoryStream,
+) -> *mut C2paStream {
+    catch_panic(std::ptr::null_mut(), || {
+        Ok(&mut deref_mut(memory, "memory")?.stream as *mut C2paStream)
+    })
+}
+
+/// Creates a seekable C2paStream over a forward-only source
+///
+/// The source can only be read from start to end, such as an upload being received.
+/// It is only read as far as needed. What has been read is kept in memory
+/// up to the threshold, and then moved to a temporary file that is deleted with the stream
+///
+/// # Arguments
+/// * `context` - a pointer to a StreamContext
+/// * `read` - a ReadCallback to read from the source
+/// * `close` - an optional CloseCallback, called when the stream is released, may be NULL
+/// * `threshold` - the number of bytes to keep in memory before spooling to a file
+///
+/// # Returns
+/// * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// The context must remain valid for the lifetime of the C2paStream.
+/// Once the context and read callback have been checked, the context belongs
+/// to the spool stream: if this call fails after that, the close callback is called.
+/// The resulting C2paStream must be released by calling c2pa_release_stream
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_spool_stream(
+    context: *mut StreamContext,
+    read: Option<ReadCallback>,
+    close: Option<CloseCallback>,
+    threshold: u64,
+) -> *mut C2paStream {
+    catch_panic(std::ptr::null_mut(), || {
+        if context.is_null() {
+            return Err(C2paError::NullParameter("context".to_string()));
+        }
+        let read = callback(read, "read")?;
+        let mut source =
+            C2paStream::with_seek64(context, read, forward_only_seek, forward_only_write);
+        source.close_callback = close;
+        let source: Box<dyn crate::Stream> = Box::new(source);
+        Ok(native_stream(
+            SpoolStream::new(source, threshold).map_err(|e| C2paError::Other(e.to_string()))?,
+        ))
+    })
+}
+
+/// Copies the contents of a memory stream into a buffer
+///
+/// Call with a NULL buffer to get the size of the contents
+///
+/// # Arguments
+/// * `memory` - a C2paMemoryStream created by c2pa_create_memory_stream
+/// * `buffer` - the buffer to copy into, may be NULL if len is 0
+/// * `len` - the size of the buffer
+///
+/// # Returns
+/// * the size of the contents, which is more than len if the buffer was too small,
+///   or a negative value with the error available from c2pa_error
+///
+/// # Safety
+/// The buffer must be valid for len bytes
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_memory_stream_data(
+    memory: *const C2paMemoryStream,
+    buffer: *mut u8,
+    len: usize,
+) -> isize {
+    catch_panic(-1, || {
+        let memory = deref(memory, "memory")?.memory();
+        let io_error = |e: StreamError| C2paError::Io(e.to_string());
+        memory
+            .copy_data(0, buffer_mut(buffer, len, "buffer")?)
+            .map_err(io_error)?;
+        Ok(memory.size().map_err(io_error)? as isize)
+    })
+}
+
+/// Detects the format of an asset from the content of a stream
+///
+/// # Returns
+/// The mime type of the asset or NULL if the format could not be detected
+///
+/// # Safety
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_detect_format(stream: *mut C2paStream) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        Ok(to_c_string(format_from_reader(&mut stream)?))
+    })
+}
+
+/// Verify a stream and return a ManifestStore report
+///
+/// # Errors
+/// Returns an error field if there were errors
+///
+/// # Safety
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_verify_stream(reader: *mut C2paStream) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let mut reader = StreamAdapter::from_stream_mut(deref_mut(reader, "reader")?);
+        let manifest_store = ManifestStoreReader::new();
+        Ok(to_c_string(manifest_store.read(AUTO_FORMAT, &mut reader)?))
+    })
+}
+
+/// Create a new ManifestStoreReader
+///
+/// # Safety
+/// The returned value MUST be released by calling release_manifest_reader
+///
+/// # Example
+/// ```
+/// use c2pa::ManifestStoreReader;
+/// let reader = ManifestStoreReader::new();
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_reader_new() -> *mut ManifestStoreReader {
+    catch_panic(std::ptr::null_mut(), || {
+        let reader = ManifestStoreReader::new();
+        Ok(Box::into_raw(Box::new(reader)))
+    })
+}
+
+/// Create a new ManifestStoreReader with settings
+///
+/// # Arguments
+/// * `settings` - a pointer to a ManifestStoreReaderSettingsC
+///
+/// # Safety
+/// The returned value MUST be released by calling release_manifest_reader
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_reader_with_settings(
+    settings: *const ManifestStoreReaderSettingsC,
+) -> *mut ManifestStoreReader {
+    catch_panic(std::ptr::null_mut(), || {
+        let settings = deref(settings, "settings")?;
+        let settings = ManifestStoreReaderSettings {
+            max_stream_size: match settings.max_stream_size {
+                0 => None,
+                size => Some(size),
+            },
+        };
+        let reader = ManifestStoreReader::with_settings(&settings);
+        Ok(Box::into_raw(Box::new(reader)))
+    })
+}
+
+/// Sets the callback used to fetch remote manifest stores
+///
+/// # Arguments
+/// * `reader_ptr` - a pointer to a ManifestStoreReader
+/// * `context` - a context pointer passed to each call of the callback
+/// * `fetch` - the callback that copies the manifest store for a url into a buffer
+///
+/// The callback returns the size of the manifest store, or a negative value on error.
+/// If the size is larger than the buffer, it is called again with a large enough buffer
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// The context must remain valid, and usable from any thread, for the lifetime of the reader
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_reader_set_fetcher(
+    reader_ptr: *mut *mut ManifestStoreReader,
+    context: *const FetchContext,
+    fetch: Option<FetchCallback>,
+) -> c_int {
+    catch_panic(-1, || {
+        let reader = deref_ptr(reader_ptr, "reader_ptr")?;
+        let fetch = callback(fetch, "fetch")?;
+        reader.set_fetcher(Box::new(CManifestFetcher { context, fetch }))?;
+        Ok(0)
+    })
+}
+
+/// Read a manifest store from a stream
+///
+/// # Arguments
+/// * `reader_ptr` - a pointer to a ManifestStoreReader
+/// * `format` - the format of the manifest store, empty or "auto" to detect it
+/// * `stream` - the stream to read from
+///
+/// # Returns
+/// * `Result<String>` - the json representation of the manifest store
+///
+/// # Example
+/// ```
+/// use c2pa::ManifestStoreReader;
+/// use std::io::Cursor;
+///     
+/// let reader = ManifestStoreReader::new();
 /// let mut stream = Cursor::new("test".as_bytes());
 /// let json = reader.read("image/jpeg", &mut stream);
 /// ```
@@ -355,19 +1471,47 @@ pub unsafe extern "C" fn c2pa_manifest_reader_read(
     format: *const c_char,
     stream: *mut C2paStream,
 ) -> *mut c_char {
-    let reader = Box::from_raw(*reader_ptr);
-    let mut stream = StreamAdapter::from_stream_mut(&mut (*stream));
-    let format = from_c_str(format);
-    let result = reader.read(&format, &mut stream);
-    let str = match result {
-        Ok(json) => json,
-        Err(e) => {
-            e.set_last();
-            return std::ptr::null_mut();
-        }
-    };
-    *reader_ptr = Box::into_raw(reader);
-    to_c_string(str)
+    catch_panic(std::ptr::null_mut(), || {
+        let reader = deref_ptr(reader_ptr, "reader_ptr")?;
+        let format = from_c_str(format, "format")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        Ok(to_c_string(reader.read(&format, &mut stream)?))
+    })
+}
+
+/// Read a sidecar manifest store and validate it against an asset stream
+///
+/// # Arguments
+/// * `reader_ptr` - a pointer to a ManifestStoreReader
+/// * `format` - the format of the asset, empty or "auto" to detect it
+/// * `manifest_stream` - the stream to read the manifest store (.c2pa) from
+/// * `asset_stream` - the stream to read the asset from
+///
+/// # Returns
+/// * `Result<String>` - the json representation of the manifest store
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_reader_read_with_manifest(
+    reader_ptr: *mut *mut ManifestStoreReader,
+    format: *const c_char,
+    manifest_stream: *mut C2paStream,
+    asset_stream: *mut C2paStream,
+) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let reader = deref_ptr(reader_ptr, "reader_ptr")?;
+        let format = from_c_str(format, "format")?;
+        let mut manifest_stream =
+            StreamAdapter::from_stream_mut(deref_mut(manifest_stream, "manifest_stream")?);
+        let mut asset_stream =
+            StreamAdapter::from_stream_mut(deref_mut(asset_stream, "asset_stream")?);
+        let json = reader.read_with_manifest(&format, &mut manifest_stream, &mut asset_stream)?;
+        Ok(to_c_string(json))
+    })
 }
 
 /// Writes a resource from the manifest reader to a stream
@@ -401,16 +1545,141 @@ pub unsafe extern "C" fn c2pa_manifest_reader_resource(
     id: *const c_char,
     stream: *mut C2paStream,
 ) {
-    let reader = Box::from_raw(*reader_ptr);
-    //let stream = &mut *stream;
-    let mut stream = StreamAdapter::from_stream_mut(&mut (*stream));
-    let manifest_label = from_c_str(manifest_label);
-    let id = from_c_str(id);
-    let result = reader.resource_write(&manifest_label, &id, &mut stream);
-    if let Err(e) = result {
-        e.set_last();
-    }
-    *reader_ptr = Box::into_raw(reader);
+    catch_panic((), || {
+        let reader = deref_ptr(reader_ptr, "reader_ptr")?;
+        let manifest_label = from_c_str(manifest_label, "manifest_label")?;
+        let id = from_c_str(id, "id")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        reader.resource_write(&manifest_label, &id, &mut stream)
+    })
+}
+
+/// Create a new IngredientBuilder
+///
+/// # Arguments
+/// * `settings` - a pointer to an IngredientBuilderSettingsC
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The returned value MUST be released by calling release_ingredient_builder
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_create_ingredient_builder(
+    settings: *const IngredientBuilderSettingsC,
+) -> *mut IngredientBuilder {
+    catch_panic(std::ptr::null_mut(), || {
+        let settings = deref(settings, "settings")?;
+        let settings = IngredientBuilderSettings {
+            title: from_c_str(settings.title, "title")?,
+            format: from_c_str(settings.format, "format")?,
+            instance_id: from_c_str(settings.instance_id, "instance_id")?,
+            relationship: settings.relationship,
+        };
+        let builder = IngredientBuilder::new(&settings);
+        Ok(Box::into_raw(Box::new(builder)))
+    })
+}
+
+/// Replaces the ingredient of an IngredientBuilder from an ingredient definition
+///
+/// Resources of the current ingredient are kept when the definition still refers to them
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to an IngredientBuilder
+/// * `json` - a pointer to a null terminated JSON ingredient definition
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_ingredient_builder_from_json(
+    builder_ptr: *mut *mut IngredientBuilder,
+    json: *const c_char,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        builder.from_json(&from_c_str(json, "json")?)?;
+        Ok(0)
+    })
+}
+
+/// Read an ingredient from a stream
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to an IngredientBuilder
+/// * `format` - the format of the asset, empty or "auto" to detect it
+/// * `stream` - the stream to read from
+///
+/// # Returns
+/// * the json representation of the ingredient, or NULL with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_ingredient_builder_read(
+    builder_ptr: *mut *mut IngredientBuilder,
+    format: *const c_char,
+    stream: *mut C2paStream,
+) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let format = from_c_str(format, "format")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        Ok(to_c_string(builder.read(&format, &mut stream)?))
+    })
+}
+
+/// Returns the json representation of an ingredient
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to an IngredientBuilder
+///
+/// # Safety
+/// The returned value MUST be released by calling release_string
+/// and it is no longer valid after that call.
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_ingredient_builder_json(
+    builder_ptr: *mut *mut IngredientBuilder,
+) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        Ok(to_c_string(builder.json()?))
+    })
+}
+
+/// Writes a resource of an ingredient to a stream
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to an IngredientBuilder
+/// * `id` - the resource identifier
+/// * `stream` - the stream to write the resource to
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_ingredient_builder_resource(
+    builder_ptr: *mut *mut IngredientBuilder,
+    id: *const c_char,
+    stream: *mut C2paStream,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let id = from_c_str(id, "id")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        builder.resource_write(&id, &mut stream)?;
+        Ok(0)
+    })
 }
 
 /// Create a ManifestBuilder
@@ -435,6 +1704,7 @@ pub unsafe extern "C" fn c2pa_manifest_reader_resource(
 /// }"#;
 /// let settings = ManifestBuilderSettings {
 ///    generator: "test".to_string(),
+///    sidecar: false,
 /// };
 ///     
 ///   let builder = ManifestBuilder::new(&settings);
@@ -444,22 +1714,107 @@ pub unsafe extern "C" fn c2pa_manifest_reader_resource(
 
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_create_manifest_builder(
-    settings: &ManifestBuilderSettingsC,
+    settings: *const ManifestBuilderSettingsC,
     json: *const c_char,
 ) -> *mut ManifestBuilder {
-    let json = from_c_str(json);
-    let settings = ManifestBuilderSettings {
-        generator: from_c_str(settings.claim_generator),
-    };
-    let builder = ManifestBuilder::new(&settings);
-    builder.from_json(&json).unwrap();
-    match builder.from_json(&json) {
-        Ok(_) => Box::into_raw(Box::new(builder)),
-        Err(e) => {
-            e.set_last();
-            std::ptr::null_mut()
-        }
-    }
+    catch_panic(std::ptr::null_mut(), || {
+        let settings = deref(settings, "settings")?;
+        let settings = ManifestBuilderSettings {
+            generator: from_c_str(settings.claim_generator, "claim_generator")?,
+            sidecar: settings.sidecar,
+        };
+        let json = from_c_str(json, "json")?;
+        let builder = ManifestBuilder::new(&settings);
+        builder.from_json(&json)?;
+        Ok(Box::into_raw(Box::new(builder)))
+    })
+}
+
+/// Adds a resource to a ManifestBuilder from a stream
+///
+/// The resource is read to the end and stored as raw bytes under its identifier.
+/// Its mime type comes from the manifest definition that references the identifier,
+/// such as the format of a thumbnail or icon
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to a ManifestBuilder
+/// * `id` - the resource identifier referenced in the manifest definition
+/// * `stream` - the stream to read the resource from
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_add_resource(
+    builder_ptr: *mut *mut ManifestBuilder,
+    id: *const c_char,
+    stream: *mut C2paStream,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let id = from_c_str(id, "id")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        builder.add_resource_reader(&id, &mut stream)?;
+        Ok(0)
+    })
+}
+
+/// Adds an ingredient read by an IngredientBuilder to a ManifestBuilder
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to a ManifestBuilder
+/// * `ingredient` - a pointer to an IngredientBuilder
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// The ingredient must be a valid IngredientBuilder, it is not released by this call
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_add_ingredient(
+    builder_ptr: *mut *mut ManifestBuilder,
+    ingredient: *const IngredientBuilder,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        builder.add_ingredient(deref(ingredient, "ingredient")?)?;
+        Ok(0)
+    })
+}
+
+/// Adds an ingredient to a ManifestBuilder from a stream
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to a ManifestBuilder
+/// * `json` - an ingredient definition, with fields that replace those read from the stream
+/// * `format` - the format of the ingredient, empty or "auto" to detect it
+/// * `stream` - the stream to read the ingredient from
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_add_ingredient_stream(
+    builder_ptr: *mut *mut ManifestBuilder,
+    json: *const c_char,
+    format: *const c_char,
+    stream: *mut C2paStream,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let json = from_c_str(json, "json")?;
+        let format = from_c_str(format, "format")?;
+        let mut stream = StreamAdapter::from_stream_mut(deref_mut(stream, "stream")?);
+        builder.add_ingredient_reader(&json, &format, &mut stream)?;
+        Ok(0)
+    })
 }
 
 #[no_mangle]
@@ -469,7 +1824,13 @@ pub unsafe extern "C" fn c2pa_create_manifest_builder(
 /// * `builder` - a pointer to a ManifestBuilder
 /// * `signer` - a pointer to a C2paSigner
 /// * `input` - a pointer to a C2paStream
-/// * `output` - optional pointer to a C2paStream
+/// * `output` - a pointer to a C2paStream to write the signed asset to, must not be NULL
+///
+/// If the builder was created with the sidecar setting, the output receives
+/// the manifest store (.c2pa) and the asset is not modified
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
 ///
 pub unsafe extern "C" fn c2pa_manifest_builder_sign(
     builder_ptr: *mut *mut ManifestBuilder,
@@ -477,18 +1838,122 @@ pub unsafe extern "C" fn c2pa_manifest_builder_sign(
     input: *mut C2paStream,
     output: *mut C2paStream,
 ) -> c_int {
-    let builder = Box::from_raw(*builder_ptr);
-    let mut input_ref = StreamAdapter::from_stream_mut(&mut (*input));
-    let mut output_ref = StreamAdapter::from_stream_mut(&mut (*output));
-    let result = builder.sign(&(*signer), &mut input_ref, &mut output_ref);
-    *builder_ptr = Box::into_raw(builder);
-    match result {
-        Ok(_) => 0,
-        Err(e) => {
-            e.set_last();
-            -1
-        }
-    }
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let signer = deref(signer, "signer")?;
+        let mut input_ref = StreamAdapter::from_stream_mut(deref_mut(input, "input")?);
+        let mut output_ref = StreamAdapter::from_stream_mut(deref_mut(output, "output")?);
+        builder.sign(signer, &mut input_ref, &mut output_ref)?;
+        Ok(0)
+    })
+}
+
+/// Sign using a ManifestBuilder, writing the signed asset sequentially
+///
+/// The output is only written to and flushed, never read or seeked,
+/// so it can be created with c2pa_create_write_stream.
+/// The signed asset is staged in a temporary file before it is written.
+/// The input must be seekable, a forward-only input can be read through c2pa_create_spool_stream
+///
+/// # Arguments
+/// * `builder` - a pointer to a ManifestBuilder
+/// * `signer` - a pointer to a C2paSigner
+/// * `input` - a pointer to a seekable C2paStream
+/// * `output` - a pointer to a C2paStream, which may be write-only
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// All streams must be valid C2paStreams
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_sign_sequential(
+    builder_ptr: *mut *mut ManifestBuilder,
+    signer: *const C2paSigner,
+    input: *mut C2paStream,
+    output: *mut C2paStream,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let signer = deref(signer, "signer")?;
+        let mut input_ref = StreamAdapter::from_stream_mut(deref_mut(input, "input")?);
+        let mut output_ref = StreamAdapter::from_stream_mut(deref_mut(output, "output")?);
+        builder.sign_sequential(signer, &mut input_ref, &mut output_ref)?;
+        Ok(0)
+    })
+}
+
+/// Sets a remote url for the manifest store of a ManifestBuilder
+///
+/// # Arguments
+/// * `builder_ptr` - a pointer to a ManifestBuilder
+/// * `url` - the url where the manifest store will be available
+/// * `remote_only` - if true, the manifest is not embedded in the asset
+///
+/// The url is written to the XMP of the signed asset. Use
+/// c2pa_manifest_builder_sign_with_manifest to get the manifest store to upload
+///
+/// Only JPEG assets are currently supported: signing other formats fails
+/// with a NotSupported error, and the asset is held in memory while the
+/// reference is written to its XMP
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// Reads from null terminated C strings
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_set_remote_url(
+    builder_ptr: *mut *mut ManifestBuilder,
+    url: *const c_char,
+    remote_only: bool,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        builder.set_remote_url(&from_c_str(url, "url")?, remote_only)?;
+        Ok(0)
+    })
+}
+
+/// Sign using a ManifestBuilder, writing the manifest store to a stream
+///
+/// # Arguments
+/// * `builder` - a pointer to a ManifestBuilder
+/// * `signer` - a pointer to a C2paSigner
+/// * `input` - a pointer to a C2paStream
+/// * `output` - a pointer to a C2paStream
+/// * `manifest_output` - a pointer to a C2paStream to receive the manifest store
+///
+/// The manifest store is what must be uploaded when a remote url is set
+///
+/// # Returns
+/// * `0` if successful, otherwise `-1` with the error available from c2pa_error
+///
+/// # Safety
+/// All streams must be valid C2paStreams
+///
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_manifest_builder_sign_with_manifest(
+    builder_ptr: *mut *mut ManifestBuilder,
+    signer: *const C2paSigner,
+    input: *mut C2paStream,
+    output: *mut C2paStream,
+    manifest_output: *mut C2paStream,
+) -> c_int {
+    catch_panic(-1, || {
+        let builder = deref_ptr(builder_ptr, "builder_ptr")?;
+        let signer = deref(signer, "signer")?;
+        let mut input_ref = StreamAdapter::from_stream_mut(deref_mut(input, "input")?);
+        let mut output_ref = StreamAdapter::from_stream_mut(deref_mut(output, "output")?);
+        let mut manifest_ref =
+            StreamAdapter::from_stream_mut(deref_mut(manifest_output, "manifest_output")?);
+        let manifest_bytes = builder.sign(signer, &mut input_ref, &mut output_ref)?;
+        manifest_ref.write_all(&manifest_bytes)?;
+        manifest_ref.flush()?;
+        Ok(0)
+    })
 }
 
 /// Releases a string allocated by Rust
@@ -499,10 +1964,12 @@ pub unsafe extern "C" fn c2pa_manifest_builder_sign(
 /// can only be released once and is invalid after this call
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_release_string(s: *mut c_char) {
-    if s.is_null() {
-        return;
-    }
-    drop(CString::from_raw(s));
+    catch_panic((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+        Ok(())
+    })
 }
 
 /// Releases a C2paStream allocated by Rust
@@ -513,10 +1980,27 @@ pub unsafe extern "C" fn c2pa_release_string(s: *mut c_char) {
 /// can only be released once and is invalid after this call
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_release_stream(stream: *mut C2paStream) {
-    if stream.is_null() {
-        return;
-    }
-    drop(Box::from_raw(stream));
+    catch_panic((), || {
+        if !stream.is_null() {
+            drop(Box::from_raw(stream));
+        }
+        Ok(())
+    })
+}
+
+/// Releases a C2paMemoryStream allocated by Rust
+///
+/// # Safety
+/// can only be released once and is invalid after this call,
+/// along with the C2paStream returned by c2pa_memory_stream_as_stream
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_release_memory_stream(memory: *mut C2paMemoryStream) {
+    catch_panic((), || {
+        if !memory.is_null() {
+            drop(Box::from_raw(memory));
+        }
+        Ok(())
+    })
 }
 
 /// Releases a ManifestStoreReader allocated by Rust
@@ -525,10 +2009,12 @@ pub unsafe extern "C" fn c2pa_release_stream(stream: *mut C2paStream) {
 /// can only be released once and is invalid after this call
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_release_manifest_reader(reader: *mut ManifestStoreReader) {
-    if reader.is_null() {
-        return;
-    }
-    drop(Box::from_raw(reader));
+    catch_panic((), || {
+        if !reader.is_null() {
+            drop(Box::from_raw(reader));
+        }
+        Ok(())
+    })
 }
 
 /// Releases a ManifestBuilder allocated by Rust
@@ -537,10 +2023,41 @@ pub unsafe extern "C" fn c2pa_release_manifest_reader(reader: *mut ManifestStore
 /// can only be released once and is invalid after this call
 #[no_mangle]
 pub unsafe extern "C" fn c2pa_release_manifest_builder(builder: *mut ManifestBuilder) {
-    if builder.is_null() {
-        return;
-    }
-    drop(Box::from_raw(builder));
+    catch_panic((), || {
+        if !builder.is_null() {
+            drop(Box::from_raw(builder));
+        }
+        Ok(())
+    })
+}
+
+/// Releases an IngredientBuilder allocated by Rust
+///
+/// # Safety
+/// can only be released once and is invalid after this call
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_release_ingredient_builder(builder: *mut IngredientBuilder) {
+    catch_panic((), || {
+        if !builder.is_null() {
+            drop(Box::from_raw(builder));
+        }
+        Ok(())
+    })
+}
+
+/// Releases a C2paSigner allocated by Rust
+///
+/// # Safety
+/// can only be released once and is invalid after this call
+/// The signer must not be in use by another thread
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_release_signer(signer: *mut C2paSigner) {
+    catch_panic((), || {
+        if !signer.is_null() {
+            drop(Box::from_raw(signer));
+        }
+        Ok(())
+    })
 }
 
 // pub unsafe extern "C" fn c2pa_release_box(object: *mut std::ffi::c_void) {
@@ -549,3 +2066,556 @@ pub unsafe extern "C" fn c2pa_release_manifest_builder(builder: *mut ManifestBui
 //     }
 //     drop(Box::from_raw(object));
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::test_signer::TestSigner;
+
+    unsafe extern "C" fn failing_read(
+        _context: *const StreamContext,
+        _data: *mut u8,
+        _len: usize,
+    ) -> isize {
+        c2pa_set_callback_error(5, c"disk on fire".as_ptr());
+        -1
+    }
+
+    unsafe extern "C" fn failing_seek(
+        _context: *const StreamContext,
+        _offset: i64,
+        _mode: SeekMode,
+    ) -> i64 {
+        -1
+    }
+
+    unsafe extern "C" fn failing_write(
+        _context: *const StreamContext,
+        _data: *const u8,
+        _len: usize,
+    ) -> isize {
+        -1
+    }
+
+    unsafe extern "C" fn failing_signer(
+        _data: *mut u8,
+        _len: usize,
+        _signature: *mut u8,
+        _sig_max_size: isize,
+    ) -> isize {
+        c2pa_set_callback_error(-2, c"key locked".as_ptr());
+        -1
+    }
+
+    fn failing_stream() -> C2paStream {
+        let context = Box::into_raw(Box::new(StreamContext { _priv: () }));
+        unsafe { C2paStream::with_seek64(context, failing_read, failing_seek, failing_write) }
+    }
+
+    #[test]
+    fn test_stream_callback_errors() {
+        let stream = failing_stream();
+        let result = crate::Stream::read_stream(&stream, 10);
+        assert!(
+            matches!(result, Err(StreamError::Io { reason }) if reason == "disk on fire (code 5)")
+        );
+
+        // without a reported error a default message is used
+        let result = crate::Stream::seek_stream(&stream, 0, SeekMode::End);
+        assert!(
+            matches!(result, Err(StreamError::Io { reason }) if reason == "stream seek failed")
+        );
+        let result = crate::Stream::write_stream(&stream, vec![0; 4]);
+        assert!(matches!(result, Err(StreamError::Io { .. })));
+    }
+
+    unsafe extern "C" fn fixed_length(_context: *const StreamContext) -> i64 {
+        42
+    }
+
+    #[test]
+    fn test_stream_set_length_callback() {
+        let mut stream = failing_stream();
+        assert_eq!(crate::Stream::stream_length(&stream).unwrap(), None);
+        let result = unsafe { c2pa_stream_set_length_callback(&mut stream, Some(fixed_length)) };
+        assert_eq!(result, 0);
+        assert_eq!(crate::Stream::stream_length(&stream).unwrap(), Some(42));
+        let result = unsafe { c2pa_stream_set_length_callback(&mut stream, None) };
+        assert_eq!(result, 0);
+        assert_eq!(crate::Stream::stream_length(&stream).unwrap(), None);
+        let result =
+            unsafe { c2pa_stream_set_length_callback(std::ptr::null_mut(), Some(fixed_length)) };
+        assert_eq!(result, -1);
+    }
+
+    // Seek callbacks that move to the offset they are given
+    unsafe extern "C" fn long_seek_to(
+        _context: *const StreamContext,
+        offset: c_long,
+        _mode: SeekMode,
+    ) -> c_int {
+        offset as c_int
+    }
+
+    unsafe extern "C" fn seek64_to(
+        _context: *const StreamContext,
+        offset: i64,
+        _mode: SeekMode,
+    ) -> i64 {
+        offset
+    }
+
+    #[test]
+    fn test_stream_seek_callbacks() {
+        let context = Box::into_raw(Box::new(StreamContext { _priv: () }));
+        let stream = unsafe {
+            c2pa_create_stream(
+                context,
+                Some(failing_read),
+                Some(long_seek_to),
+                Some(failing_write),
+            )
+        };
+        let result = crate::Stream::seek_stream(unsafe { &*stream }, 7, SeekMode::Start);
+        assert_eq!(result.unwrap(), 7);
+        unsafe { c2pa_release_stream(stream) };
+
+        let callbacks = C2paStreamCallbacks {
+            read: Some(failing_read),
+            seek: Some(seek64_to),
+            write: Some(failing_write),
+            flush: None,
+            close: None,
+            length: Some(fixed_length),
+        };
+        let context = Box::into_raw(Box::new(StreamContext { _priv: () }));
+        let stream = unsafe { c2pa_create_stream_with_callbacks(context, &callbacks) };
+        let offset = 3 << 30;
+        let result = crate::Stream::seek_stream(unsafe { &*stream }, offset, SeekMode::Start);
+        assert_eq!(result.unwrap(), offset as u64);
+        let length = crate::Stream::stream_length(unsafe { &*stream }).unwrap();
+        assert_eq!(length, Some(42));
+        unsafe { c2pa_release_stream(stream) };
+
+        let callbacks = C2paStreamCallbacks {
+            seek: None,
+            ..callbacks
+        };
+        let context = Box::into_raw(Box::new(StreamContext { _priv: () }));
+        let stream = unsafe { c2pa_create_stream_with_callbacks(context, &callbacks) };
+        assert!(stream.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::NullParameter);
+        drop(unsafe { Box::from_raw(context) });
+    }
+
+    #[test]
+    fn test_signer_callback_error() {
+        let builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(r#"{"title": "test", "format": "image/jpeg"}"#)
+            .unwrap();
+        let signer = C2paSigner::new(Box::new(CSignerCallback {
+            signer: failing_signer,
+        }));
+        signer.configure(&TestSigner::new().config()).unwrap();
+        let mut input = std::io::Cursor::new(include_bytes!("../tests/fixtures/A.jpg").to_vec());
+        let mut output = std::io::Cursor::new(Vec::new());
+        let result = builder.sign(&signer, &mut input, &mut output);
+        assert!(
+            matches!(result, Err(C2paError::Signature { ref reason, .. }) if reason.contains("key locked (code -2)")),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_error_code() {
+        C2paError::take_last();
+        assert_eq!(c2pa_error_code(), C2paErrorCode::None);
+        assert!(unsafe { c2pa_error_details_json() }.is_null());
+
+        C2paError::Json("bad".to_string()).set_last();
+        assert_eq!(c2pa_error_code(), C2paErrorCode::Json);
+        let json = unsafe { c2pa_error_details_json() };
+        let details = unsafe { CString::from_raw(json) }.into_string().unwrap();
+        let details: serde_json::Value = serde_json::from_str(&details).unwrap();
+        assert_eq!(details["variant"], "Json");
+        assert_eq!(details["message"], "Json bad");
+        assert_eq!(details["validation_codes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_error_callback_code() {
+        let details = || {
+            let json = unsafe { c2pa_error_details_json() };
+            let details = unsafe { CString::from_raw(json) }.into_string().unwrap();
+            serde_json::from_str::<serde_json::Value>(&details).unwrap()
+        };
+        let stream = failing_stream();
+        let result = catch_panic(-1, || {
+            crate::Stream::read_stream(&stream, 10)
+                .map(|_| 0)
+                .map_err(|e| C2paError::Io(e.to_string()))
+        });
+        assert_eq!(result, -1);
+        assert_eq!(C2paError::last_callback_code(), Some(5));
+        assert_eq!(details()["callback_code"], 5);
+
+        // the code does not outlive the call that failed
+        let result = catch_panic(-1, || Err(C2paError::Json("bad".to_string())));
+        assert_eq!(result, -1);
+        assert!(details()["callback_code"].is_null());
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        let result = catch_panic(-1, || panic!("oops"));
+        assert_eq!(result, -1);
+        assert_eq!(c2pa_error_code(), C2paErrorCode::Panic);
+        assert!(matches!(C2paError::take_last(), Some(C2paError::Panic(m)) if m == "oops"));
+        assert_eq!(catch_panic(-1, || Ok(0)), 0);
+    }
+
+    #[test]
+    fn test_create_manifest_builder_bad_json() {
+        let settings = ManifestBuilderSettingsC {
+            claim_generator: c"test".as_ptr(),
+            sidecar: false,
+        };
+        let builder = unsafe { c2pa_create_manifest_builder(&settings, c"{".as_ptr()) };
+        assert!(builder.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::Json);
+    }
+
+    #[test]
+    fn test_null_parameters() {
+        let settings = ManifestBuilderSettingsC {
+            claim_generator: c"test".as_ptr(),
+            sidecar: false,
+        };
+        let builder = unsafe { c2pa_create_manifest_builder(&settings, std::ptr::null()) };
+        assert!(builder.is_null());
+        assert!(matches!(C2paError::take_last(), Some(C2paError::NullParameter(n)) if n == "json"));
+
+        let mut reader = unsafe { c2pa_manifest_reader_new() };
+        let json = unsafe {
+            c2pa_manifest_reader_read(&mut reader, c"auto".as_ptr(), std::ptr::null_mut())
+        };
+        assert!(json.is_null());
+        assert!(
+            matches!(C2paError::take_last(), Some(C2paError::NullParameter(n)) if n == "stream")
+        );
+        unsafe { c2pa_release_manifest_reader(reader) };
+
+        let result = unsafe {
+            c2pa_manifest_builder_sign(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(result, -1);
+        assert_eq!(c2pa_error_code(), C2paErrorCode::NullParameter);
+
+        let signer = unsafe { c2pa_create_signer(None, std::ptr::null()) };
+        assert!(signer.is_null());
+        assert!(
+            matches!(C2paError::take_last(), Some(C2paError::NullParameter(n)) if n == "signer")
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let settings = ManifestBuilderSettingsC {
+            claim_generator: c"test\xff".as_ptr(),
+            sidecar: false,
+        };
+        let builder = unsafe { c2pa_create_manifest_builder(&settings, c"{}".as_ptr()) };
+        assert!(builder.is_null());
+        assert!(
+            matches!(C2paError::take_last(), Some(C2paError::Decoding(m)) if m.starts_with("claim_generator"))
+        );
+    }
+
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn count_release(_context: *const SignerContext) {
+        RELEASED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe extern "C" fn context_signer(
+        _context: *const SignerContext,
+        _data: *const u8,
+        _len: usize,
+        _signature: *mut u8,
+        _sig_max_size: usize,
+    ) -> isize {
+        -1
+    }
+
+    unsafe extern "C" fn oversized_signer(
+        _context: *const SignerContext,
+        _data: *const u8,
+        _len: usize,
+        _signature: *mut u8,
+        sig_max_size: usize,
+    ) -> isize {
+        sig_max_size as isize + 1
+    }
+
+    #[test]
+    fn test_signer_oversized_signature() {
+        let signer = CSignerContextCallback {
+            context: std::ptr::null(),
+            signer: oversized_signer,
+            release: None,
+        };
+        let result = crate::SignerCallback::sign(&signer, vec![0; 4]);
+        assert!(
+            matches!(result, Err(StreamError::Other { ref reason }) if reason.contains("more than")),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_release_signer() {
+        let config = SignerConfigC {
+            alg: c"es256".as_ptr(),
+            certs: c"".as_ptr(),
+            time_authority_url: std::ptr::null(),
+            use_ocsp: false,
+        };
+        let context = c"key".as_ptr() as *const SignerContext;
+        for _ in 0..3 {
+            let signer = unsafe {
+                c2pa_create_signer_with_context(
+                    context,
+                    Some(context_signer),
+                    Some(count_release),
+                    &config,
+                )
+            };
+            assert!(!signer.is_null());
+            unsafe { c2pa_release_signer(signer) };
+        }
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 3);
+        unsafe { c2pa_release_signer(std::ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_open_file_stream() {
+        let stream =
+            unsafe { c2pa_open_file_stream(c"tests/fixtures/C.jpg".as_ptr(), c"rb".as_ptr()) };
+        assert!(!stream.is_null());
+        let mut reader = unsafe { c2pa_manifest_reader_new() };
+        let json = unsafe { c2pa_manifest_reader_read(&mut reader, c"auto".as_ptr(), stream) };
+        assert!(!json.is_null());
+        unsafe {
+            c2pa_release_string(json);
+            c2pa_release_manifest_reader(reader);
+            c2pa_release_stream(stream);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().join("does_not_exist.jpg").to_str().unwrap()).unwrap();
+        let stream = unsafe { c2pa_open_file_stream(path.as_ptr(), c"rb".as_ptr()) };
+        assert!(stream.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::Io);
+    }
+
+    #[test]
+    fn test_memory_stream() {
+        let image = std::fs::read("tests/fixtures/C.jpg").unwrap();
+        let memory = unsafe { c2pa_create_memory_stream(image.as_ptr(), image.len()) };
+        assert!(!memory.is_null());
+        let stream = unsafe { c2pa_memory_stream_as_stream(memory) };
+        let mut reader = unsafe { c2pa_manifest_reader_new() };
+        let json = unsafe { c2pa_manifest_reader_read(&mut reader, c"auto".as_ptr(), stream) };
+        assert!(!json.is_null());
+
+        let size = unsafe { c2pa_memory_stream_data(memory, std::ptr::null_mut(), 0) };
+        assert_eq!(size, image.len() as isize);
+        let length = crate::Stream::stream_length(unsafe { &*stream }).unwrap();
+        assert_eq!(length, Some(image.len() as u64));
+        let mut data = vec![0u8; size as usize];
+        let size = unsafe { c2pa_memory_stream_data(memory, data.as_mut_ptr(), data.len()) };
+        assert_eq!(size, image.len() as isize);
+        assert_eq!(data, image);
+        unsafe {
+            c2pa_release_string(json);
+            c2pa_release_manifest_reader(reader);
+            c2pa_release_memory_stream(memory);
+        }
+
+        let memory = unsafe { c2pa_create_memory_stream(std::ptr::null(), 0) };
+        assert_eq!(
+            unsafe { c2pa_memory_stream_data(memory, std::ptr::null_mut(), 0) },
+            0
+        );
+        // positions past 2 GiB are not truncated by the seek callback
+        let stream = unsafe { &*c2pa_memory_stream_as_stream(memory) };
+        let pos = crate::Stream::seek_stream(stream, 3 << 30, SeekMode::Start);
+        assert_eq!(pos.unwrap(), 3 << 30);
+        unsafe { c2pa_release_memory_stream(memory) };
+        assert!(unsafe { c2pa_create_memory_stream(std::ptr::null(), 1) }.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::NullParameter);
+
+        let size = unsafe { c2pa_memory_stream_data(std::ptr::null(), std::ptr::null_mut(), 0) };
+        assert_eq!(size, -1);
+        assert_eq!(c2pa_error_code(), C2paErrorCode::NullParameter);
+        assert!(unsafe { c2pa_memory_stream_as_stream(std::ptr::null_mut()) }.is_null());
+    }
+
+    unsafe extern "C" fn write_to_vec(
+        context: *const StreamContext,
+        data: *const u8,
+        len: usize,
+    ) -> isize {
+        let output = &mut *(context as *mut Vec<u8>);
+        output.extend_from_slice(std::slice::from_raw_parts(data, len));
+        len as isize
+    }
+
+    unsafe extern "C" fn drop_vec(context: *const StreamContext) -> c_int {
+        drop(Box::from_raw(context as *mut Vec<u8>));
+        0
+    }
+
+    #[test]
+    fn test_sign_sequential() {
+        let mut builder = unsafe {
+            c2pa_create_manifest_builder(
+                &ManifestBuilderSettingsC {
+                    claim_generator: c"test".as_ptr(),
+                    sidecar: false,
+                },
+                c"{\"title\": \"sequential\", \"format\": \"image/jpeg\"}".as_ptr(),
+            )
+        };
+        assert!(!builder.is_null());
+        let signer = unsafe {
+            c2pa_create_signer_from_keys(
+                c"es256".as_ptr(),
+                CString::new(std::fs::read_to_string("tests/fixtures/es256_certs.pem").unwrap())
+                    .unwrap()
+                    .as_ptr(),
+                CString::new(std::fs::read_to_string("tests/fixtures/es256_private.key").unwrap())
+                    .unwrap()
+                    .as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        assert!(!signer.is_null());
+        let input =
+            unsafe { c2pa_open_file_stream(c"tests/fixtures/A.jpg".as_ptr(), c"rb".as_ptr()) };
+        let signed = Box::into_raw(Box::new(Vec::<u8>::new()));
+        let output = unsafe {
+            c2pa_create_write_stream(
+                signed as *mut StreamContext,
+                Some(write_to_vec),
+                None,
+                Some(drop_vec),
+            )
+        };
+        assert!(!output.is_null());
+
+        // signing in place needs to seek in the output
+        assert_eq!(
+            unsafe { c2pa_manifest_builder_sign(&mut builder, signer, input, output) },
+            -1
+        );
+        assert!(C2paError::last_message()
+            .unwrap()
+            .contains("cannot seek in a write-only stream"));
+        unsafe { (*signed).clear() };
+
+        let result =
+            unsafe { c2pa_manifest_builder_sign_sequential(&mut builder, signer, input, output) };
+        assert_eq!(result, 0, "{:?}", C2paError::last_message());
+        let signed = unsafe { (*signed).clone() };
+        let memory = unsafe { c2pa_create_memory_stream(signed.as_ptr(), signed.len()) };
+        let stream = unsafe { c2pa_memory_stream_as_stream(memory) };
+        let mut reader = unsafe { c2pa_manifest_reader_new() };
+        let json =
+            unsafe { c2pa_manifest_reader_read(&mut reader, c"image/jpeg".as_ptr(), stream) };
+        assert!(!json.is_null());
+        assert!(unsafe { CStr::from_ptr(json) }
+            .to_string_lossy()
+            .contains("sequential"));
+        unsafe {
+            c2pa_release_string(json);
+            c2pa_release_manifest_reader(reader);
+            c2pa_release_memory_stream(memory);
+            c2pa_release_stream(input);
+            c2pa_release_stream(output);
+            c2pa_release_signer(signer);
+            c2pa_release_manifest_builder(builder);
+        }
+    }
+
+    unsafe extern "C" fn read_from_cursor(
+        context: *const StreamContext,
+        data: *mut u8,
+        len: usize,
+    ) -> isize {
+        let source = &mut *(context as *mut std::io::Cursor<Vec<u8>>);
+        source
+            .read(std::slice::from_raw_parts_mut(data, len))
+            .map_or(-1, |len| len as isize)
+    }
+
+    unsafe extern "C" fn drop_cursor(context: *const StreamContext) -> c_int {
+        drop(Box::from_raw(context as *mut std::io::Cursor<Vec<u8>>));
+        0
+    }
+
+    #[test]
+    fn test_spool_stream() {
+        let image = std::fs::read("tests/fixtures/C.jpg").unwrap();
+        let context = Box::into_raw(Box::new(std::io::Cursor::new(image)));
+        let stream = unsafe {
+            c2pa_create_spool_stream(
+                context as *mut StreamContext,
+                Some(read_from_cursor),
+                Some(drop_cursor),
+                1024,
+            )
+        };
+        assert!(!stream.is_null());
+        let mut reader = unsafe { c2pa_manifest_reader_new() };
+        let json = unsafe { c2pa_manifest_reader_read(&mut reader, c"auto".as_ptr(), stream) };
+        assert!(!json.is_null(), "{:?}", C2paError::last_message());
+        unsafe {
+            c2pa_release_string(json);
+            c2pa_release_manifest_reader(reader);
+            c2pa_release_stream(stream);
+        }
+
+        let stream = unsafe {
+            c2pa_create_spool_stream(std::ptr::null_mut(), Some(read_from_cursor), None, 0)
+        };
+        assert!(stream.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::NullParameter);
+
+        // the context is closed if the spool stream cannot be created
+        let context = Box::into_raw(Box::new(std::io::Cursor::new(Vec::<u8>::new())));
+        let stream = unsafe {
+            c2pa_create_spool_stream(
+                context as *mut StreamContext,
+                Some(read_from_cursor),
+                Some(drop_cursor),
+                u64::MAX,
+            )
+        };
+        assert!(stream.is_null());
+        assert_eq!(c2pa_error_code(), C2paErrorCode::Other);
+    }
+}
@@ -17,7 +17,38 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 // LAST_ERROR handling borrowed from Copyright (c) 2018 Michael Bryan
 thread_local! {
-    static LAST_ERROR: RefCell<Option<Error>> = RefCell::new(None);
+    static LAST_ERROR: RefCell<Option<LastError>> = RefCell::new(None);
+}
+
+// The last error, with the code reported by the C callback that caused it, if any
+struct LastError {
+    error: Error,
+    callback_code: Option<i32>,
+}
+
+/// A stable code for each Error variant, for callers that cannot match on the enum
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    None = 0,
+    Assertion = 1,
+    AssertionNotFound = 2,
+    Decoding = 3,
+    Encoding = 4,
+    FileNotFound = 5,
+    Io = 6,
+    Json = 7,
+    Manifest = 8,
+    ManifestNotFound = 9,
+    NotSupported = 10,
+    Other = 11,
+    NullParameter = 12,
+    RemoteManifest = 13,
+    ResourceNotFound = 14,
+    RwLock = 15,
+    Signature = 16,
+    Verify = 17,
+    Panic = 18,
 }
 
 #[derive(Error, Debug)]
@@ -25,8 +56,8 @@ thread_local! {
 pub enum Error {
     #[error("Assertion {0}")]
     Assertion(String),
-    #[error("AssertionNotFound {0}")]
-    AssertionNotFound(String),
+    #[error("AssertionNotFound {reason}")]
+    AssertionNotFound { reason: String, codes: Vec<String> },
     #[error("Decoding {0}")]
     Decoding(String),
     #[error("Encoding {0}")]
@@ -37,10 +68,10 @@ pub enum Error {
     Io(String),
     #[error("Json {0}")]
     Json(String),
-    #[error("Manifest {0}")]
-    Manifest(String),
-    #[error("ManifestNotFound {0}")]
-    ManifestNotFound(String),
+    #[error("Manifest {reason}")]
+    Manifest { reason: String, codes: Vec<String> },
+    #[error("ManifestNotFound {reason}")]
+    ManifestNotFound { reason: String, codes: Vec<String> },
     #[error("NotSupported {0}")]
     NotSupported(String),
     #[error("Other {0}")]
@@ -53,10 +84,12 @@ pub enum Error {
     ResourceNotFound(String),
     #[error("BindingLock")]
     RwLock,
-    #[error("Signature {0}")]
-    Signature(String),
-    #[error("Verify {0}")]
-    Verify(String),
+    #[error("Signature {reason}")]
+    Signature { reason: String, codes: Vec<String> },
+    #[error("Verify {reason}")]
+    Verify { reason: String, codes: Vec<String> },
+    #[error("Panic {0}")]
+    Panic(String),
 }
 
 impl Error {
@@ -65,8 +98,12 @@ impl Error {
     pub(crate) fn from_c2pa_error(err: c2pa::Error) -> Self {
         use c2pa::Error::*;
         let err_str = err.to_string();
+        let codes = Self::codes_from_c2pa_error(&err);
         match err {
-            c2pa::Error::AssertionMissing { url } => Self::AssertionNotFound("".to_string()),
+            c2pa::Error::AssertionMissing { url } => Self::AssertionNotFound {
+                reason: "".to_string(),
+                codes,
+            },
             AssertionInvalidRedaction
             | AssertionRedactionNotFound
             | AssertionUnsupportedVersion => Self::Assertion(err_str),
@@ -80,11 +117,20 @@ impl Error {
             | ClaimSelfRedact
             | ClaimDisallowedRedaction
             | UpdateManifestInvalid
-            | TooManyManifestStores => Self::Manifest(err_str),
-            ClaimMissing { label } => Self::ManifestNotFound(err_str),
+            | TooManyManifestStores => Self::Manifest {
+                reason: err_str,
+                codes,
+            },
+            ClaimMissing { label } => Self::ManifestNotFound {
+                reason: err_str,
+                codes,
+            },
             AssertionDecoding(_) | ClaimDecoding => Self::Decoding(err_str),
             AssertionEncoding | XmlWriteError | ClaimEncoding => Self::Encoding(err_str),
-            InvalidCoseSignature { coset_error } => Self::Signature(err_str),
+            InvalidCoseSignature { coset_error } => Self::Signature {
+                reason: err_str,
+                codes,
+            },
             CoseSignatureAlgorithmNotSupported
             | CoseMissingKey
             | CoseX5ChainMissing
@@ -99,35 +145,187 @@ impl Error {
             | CoseTimeStampGeneration
             | CoseTimeStampAuthority
             | CoseSigboxTooSmall
-            | InvalidEcdsaSignature => Self::Signature(err_str),
+            | InvalidEcdsaSignature => Self::Signature {
+                reason: err_str,
+                codes,
+            },
             RemoteManifestFetch(_) | RemoteManifestUrl(_) => Self::RemoteManifest(err_str),
-            JumbfNotFound => Self::ManifestNotFound(err_str),
+            JumbfNotFound => Self::ManifestNotFound {
+                reason: err_str,
+                codes,
+            },
             BadParam(_) | MissingFeature(_) => Self::Other(err_str),
             IoError(_) => Self::Io(err_str),
+            // signer callbacks report their errors as stream errors
+            OtherError(e) if e.is::<crate::StreamError>() => Self::Signature {
+                reason: e.to_string(),
+                codes,
+            },
             JsonError(e) => Self::Json(err_str),
             NotFound | ResourceNotFound(_) | MissingDataBox => Self::ResourceNotFound(err_str),
             FileNotFound(_) => Self::FileNotFound(err_str),
             UnsupportedType => Self::NotSupported(err_str),
-            ClaimVerification(_) | InvalidClaim(_) | JumbfParseError(_) => Self::Verify(err_str),
+            ClaimVerification(_) | InvalidClaim(_) | JumbfParseError(_) => Self::Verify {
+                reason: err_str,
+                codes,
+            },
             #[cfg(feature = "add_thumbnails")]
             ImageError => Self::ImageError(err_str),
             _ => Self::Other(err_str),
         }
     }
 
+    // The c2pa validation status codes that describe a c2pa error,
+    // attached to the variant the error maps to
+    fn codes_from_c2pa_error(err: &c2pa::Error) -> Vec<String> {
+        use c2pa::{validation_status::*, Error::*};
+        let code = match err {
+            ClaimMissing { .. } => CLAIM_MISSING,
+            AssertionMissing { .. } => ASSERTION_MISSING,
+            ClaimMissingSignatureBox => CLAIM_SIGNATURE_MISSING,
+            ClaimMissingHardBinding => HARD_BINDINGS_MISSING,
+            TooManyManifestStores => CLAIM_MULTIPLE,
+            UpdateManifestInvalid => MANIFEST_UPDATE_INVALID,
+            CoseSignature | InvalidCoseSignature { .. } => CLAIM_SIGNATURE_MISMATCH,
+            CoseSignatureAlgorithmNotSupported => ALGORITHM_UNSUPPORTED,
+            CoseMissingKey | CoseX5ChainMissing | CoseInvalidCert => SIGNING_CREDENTIAL_INVALID,
+            CoseCertExpiration => SIGNING_CREDENTIAL_EXPIRED,
+            CoseCertRevoked => SIGNING_CREDENTIAL_REVOKED,
+            CoseTimeStampMismatch => TIMESTAMP_MISMATCH,
+            CoseTimeStampValidity => TIMESTAMP_OUTSIDE_VALIDITY,
+            _ => return Vec::new(),
+        };
+        vec![code.to_string()]
+    }
+
+    /// Returns the stable code for this error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Assertion(_) => ErrorCode::Assertion,
+            Self::AssertionNotFound { .. } => ErrorCode::AssertionNotFound,
+            Self::Decoding(_) => ErrorCode::Decoding,
+            Self::Encoding(_) => ErrorCode::Encoding,
+            Self::FileNotFound(_) => ErrorCode::FileNotFound,
+            Self::Io(_) => ErrorCode::Io,
+            Self::Json(_) => ErrorCode::Json,
+            Self::Manifest { .. } => ErrorCode::Manifest,
+            Self::ManifestNotFound { .. } => ErrorCode::ManifestNotFound,
+            Self::NotSupported(_) => ErrorCode::NotSupported,
+            Self::Other(_) => ErrorCode::Other,
+            Self::NullParameter(_) => ErrorCode::NullParameter,
+            Self::RemoteManifest(_) => ErrorCode::RemoteManifest,
+            Self::ResourceNotFound(_) => ErrorCode::ResourceNotFound,
+            Self::RwLock => ErrorCode::RwLock,
+            Self::Signature { .. } => ErrorCode::Signature,
+            Self::Verify { .. } => ErrorCode::Verify,
+            Self::Panic(_) => ErrorCode::Panic,
+        }
+    }
+
+    /// Returns the name of the error variant
+    pub fn variant(&self) -> &'static str {
+        match self {
+            Self::Assertion(_) => "Assertion",
+            Self::AssertionNotFound { .. } => "AssertionNotFound",
+            Self::Decoding(_) => "Decoding",
+            Self::Encoding(_) => "Encoding",
+            Self::FileNotFound(_) => "FileNotFound",
+            Self::Io(_) => "Io",
+            Self::Json(_) => "Json",
+            Self::Manifest { .. } => "Manifest",
+            Self::ManifestNotFound { .. } => "ManifestNotFound",
+            Self::NotSupported(_) => "NotSupported",
+            Self::Other(_) => "Other",
+            Self::NullParameter(_) => "NullParameter",
+            Self::RemoteManifest(_) => "RemoteManifest",
+            Self::ResourceNotFound(_) => "ResourceNotFound",
+            Self::RwLock => "RwLock",
+            Self::Signature { .. } => "Signature",
+            Self::Verify { .. } => "Verify",
+            Self::Panic(_) => "Panic",
+        }
+    }
+
+    /// Returns the c2pa validation codes that describe this error, if it came from c2pa
+    pub fn validation_codes(&self) -> &[String] {
+        match self {
+            Self::AssertionNotFound { codes, .. }
+            | Self::Manifest { codes, .. }
+            | Self::ManifestNotFound { codes, .. }
+            | Self::Signature { codes, .. }
+            | Self::Verify { codes, .. } => codes,
+            _ => &[],
+        }
+    }
+
+    /// Returns a json description of this error
+    ///
+    /// This holds the variant, its stable code, the message and any validation codes
+    pub fn details_json(&self) -> String {
+        self.details(None)
+    }
+
+    // The json description of an error, with the code of the callback that caused it
+    fn details(&self, callback_code: Option<i32>) -> String {
+        serde_json::json!({
+            "code": self.code() as i32,
+            "variant": self.variant(),
+            "message": self.to_string(),
+            "validation_codes": self.validation_codes(),
+            "callback_code": callback_code,
+        })
+        .to_string()
+    }
+
     /// Returns the last error as String
     pub fn last_message() -> Option<String> {
-        LAST_ERROR.with(|prev| prev.borrow().as_ref().map(|e| e.to_string()))
+        LAST_ERROR.with(|prev| prev.borrow().as_ref().map(|e| e.error.to_string()))
     }
 
     /// Sets the last error
     pub fn set_last(self) {
-        LAST_ERROR.with(|prev| *prev.borrow_mut() = Some(self));
+        self.set_last_with_callback_code(None);
+    }
+
+    /// Sets the last error, with the code reported by the C callback that caused it
+    pub fn set_last_with_callback_code(self, callback_code: Option<i32>) {
+        LAST_ERROR.with(|prev| {
+            *prev.borrow_mut() = Some(LastError {
+                error: self,
+                callback_code,
+            })
+        });
+    }
+
+    /// Returns the code of the last error, or ErrorCode::None if there is none
+    pub fn last_code() -> ErrorCode {
+        LAST_ERROR.with(|prev| {
+            prev.borrow()
+                .as_ref()
+                .map_or(ErrorCode::None, |e| e.error.code())
+        })
+    }
+
+    /// Returns the code reported by the C callback that caused the last error, if any
+    pub fn last_callback_code() -> Option<i32> {
+        LAST_ERROR.with(|prev| prev.borrow().as_ref().and_then(|e| e.callback_code))
+    }
+
+    /// Returns a json description of the last error
+    ///
+    /// Along with the details of the error, this holds the code reported by
+    /// the C callback that caused it, or null
+    pub fn last_details_json() -> Option<String> {
+        LAST_ERROR.with(|prev| {
+            prev.borrow()
+                .as_ref()
+                .map(|e| e.error.details(e.callback_code))
+        })
     }
 
     /// Takes the the last error and clears it
     pub fn take_last() -> Option<Error> {
-        LAST_ERROR.with(|prev| prev.borrow_mut().take())
+        LAST_ERROR.with(|prev| prev.borrow_mut().take().map(|e| e.error))
     }
 }
 
@@ -142,3 +340,89 @@ impl From<std::io::Error> for Error {
         Self::Io(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code() {
+        let error = Error::from(c2pa::Error::JumbfNotFound);
+        assert_eq!(error.code(), ErrorCode::ManifestNotFound);
+        assert_eq!(error.variant(), "ManifestNotFound");
+        assert!(error.validation_codes().is_empty());
+
+        error.set_last();
+        assert_eq!(Error::last_code(), ErrorCode::ManifestNotFound);
+        Error::take_last();
+        assert_eq!(Error::last_code(), ErrorCode::None);
+        assert!(Error::last_details_json().is_none());
+    }
+
+    #[test]
+    fn test_error_details_json() {
+        let error = Error::from(c2pa::Error::ClaimMissing {
+            label: "test".to_string(),
+        });
+        let details: serde_json::Value = serde_json::from_str(&error.details_json()).unwrap();
+        assert_eq!(details["code"], ErrorCode::ManifestNotFound as i32);
+        assert_eq!(details["variant"], "ManifestNotFound");
+        assert_eq!(details["message"], error.to_string());
+        assert_eq!(
+            details["validation_codes"][0],
+            c2pa::validation_status::CLAIM_MISSING
+        );
+
+        // the codes are kept with the error when it becomes the last error
+        error.set_last();
+        let details: serde_json::Value =
+            serde_json::from_str(&Error::last_details_json().unwrap()).unwrap();
+        assert_eq!(
+            details["validation_codes"][0],
+            c2pa::validation_status::CLAIM_MISSING
+        );
+        Error::Io("test".to_string()).set_last();
+        let details: serde_json::Value =
+            serde_json::from_str(&Error::last_details_json().unwrap()).unwrap();
+        assert_eq!(details["validation_codes"], serde_json::json!([]));
+        assert!(details["callback_code"].is_null());
+
+        // a callback code is only reported for the last error
+        let details: serde_json::Value =
+            serde_json::from_str(&Error::Io("test".to_string()).details_json()).unwrap();
+        assert!(details["callback_code"].is_null());
+        Error::Io("test".to_string()).set_last_with_callback_code(Some(5));
+        assert_eq!(Error::last_callback_code(), Some(5));
+        let details: serde_json::Value =
+            serde_json::from_str(&Error::last_details_json().unwrap()).unwrap();
+        assert_eq!(details["callback_code"], 5);
+        Error::take_last();
+        assert_eq!(Error::last_callback_code(), None);
+    }
+
+    #[test]
+    fn test_error_codes_keep_variant() {
+        let error = Error::from(c2pa::Error::CoseCertExpiration);
+        assert_eq!(error.code(), ErrorCode::Signature);
+        assert_eq!(
+            error.validation_codes(),
+            [c2pa::validation_status::SIGNING_CREDENTIAL_EXPIRED]
+        );
+
+        let error = Error::from(c2pa::Error::AssertionMissing {
+            url: "test".to_string(),
+        });
+        assert_eq!(error.code(), ErrorCode::AssertionNotFound);
+        assert_eq!(
+            error.validation_codes(),
+            [c2pa::validation_status::ASSERTION_MISSING]
+        );
+
+        let error = Error::from(c2pa::Error::ClaimMissingHardBinding);
+        assert_eq!(error.code(), ErrorCode::Manifest);
+        assert_eq!(
+            error.validation_codes(),
+            [c2pa::validation_status::HARD_BINDINGS_MISSING]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    sync::RwLock,
+};
+
+use crate::{SeekMode, Stream, StreamError, StreamResult};
+
+/// A Stream over a file, implemented in Rust
+///
+/// Reading and writing a FileStream never calls back into the host language
+pub struct FileStream {
+    file: RwLock<File>,
+}
+
+impl FileStream {
+    /// Opens a file stream
+    /// # Arguments
+    /// * `path` - the path of the file
+    /// * `mode` - an fopen style mode: "r", "w", "a", optionally with "+" and "b"
+    /// # Returns
+    /// * `StreamResult<FileStream>` - the stream, or an error if the file cannot be opened
+    ///
+    pub fn new(path: &str, mode: &str) -> StreamResult<Self> {
+        let options = open_options(mode).ok_or_else(|| StreamError::Other {
+            reason: format!("invalid file mode {mode}"),
+        })?;
+        let file = options.open(path).map_err(|e| StreamError::Io {
+            reason: format!("{path}: {e}"),
+        })?;
+        Ok(Self {
+            file: RwLock::new(file),
+        })
+    }
+
+    // Runs a file operation while holding the lock
+    fn with_file<T>(&self, f: impl FnOnce(&mut File) -> std::io::Result<T>) -> StreamResult<T> {
+        let mut file = self.file.write().map_err(|_| StreamError::Other {
+            reason: "RwLock".to_string(),
+        })?;
+        f(&mut file).map_err(|e| StreamError::Io {
+            reason: e.to_string(),
+        })
+    }
+
+    /// Reads into a buffer, returning the number of bytes read
+    pub fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        self.with_file(|file| file.read(buf))
+    }
+
+    /// Writes a buffer, returning the number of bytes written
+    pub fn write_from(&self, buf: &[u8]) -> StreamResult<usize> {
+        self.with_file(|file| file.write(buf))
+    }
+}
+
+// Converts an fopen style mode to the equivalent OpenOptions
+fn open_options(mode: &str) -> Option<OpenOptions> {
+    let plus = mode.contains('+');
+    let mut options = OpenOptions::new();
+    match mode.replace(['b', '+'], "").as_str() {
+        "r" => options.read(true).write(plus),
+        "w" => options.write(true).create(true).truncate(true).read(plus),
+        "a" => options.append(true).create(true).read(plus),
+        _ => return None,
+    };
+    Some(options)
+}
+
+impl Stream for FileStream {
+    fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+        let mut data = vec![0u8; length as usize];
+        let len = self.read_into(&mut data)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+        let pos = match mode {
+            SeekMode::Start => SeekFrom::Start(pos as u64),
+            SeekMode::End => SeekFrom::End(pos),
+            SeekMode::Current => SeekFrom::Current(pos),
+        };
+        self.with_file(|file| file.seek(pos))
+    }
+
+    fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        self.write_from(&data).map(|len| len as u64)
+    }
+
+    fn flush_stream(&self) -> StreamResult<()> {
+        self.with_file(|file| file.flush())
+    }
+
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        self.with_file(|file| file.metadata().map(|metadata| Some(metadata.len())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_options() {
+        for mode in ["r", "rb", "r+", "rb+", "r+b", "w", "wb", "w+", "a", "ab+"] {
+            assert!(open_options(mode).is_some(), "{mode}");
+        }
+        assert!(open_options("x").is_none());
+        assert!(open_options("").is_none());
+    }
+
+    #[test]
+    fn test_file_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_file_stream.bin");
+        let path = path.to_str().unwrap();
+        let stream = FileStream::new(path, "w+b").unwrap();
+        assert_eq!(stream.write_stream(vec![1, 2, 3, 4, 5]).unwrap(), 5);
+        stream.flush_stream().unwrap();
+        assert_eq!(stream.seek_stream(1, SeekMode::Start).unwrap(), 1);
+        assert_eq!(stream.read_stream(10).unwrap(), vec![2, 3, 4, 5]);
+        assert_eq!(stream.seek_stream(-2, SeekMode::End).unwrap(), 3);
+        assert_eq!(stream.stream_length().unwrap(), Some(5));
+
+        let stream = FileStream::new(path, "rb").unwrap();
+        assert_eq!(stream.read_stream(2).unwrap(), vec![1, 2]);
+        assert!(stream.write_stream(vec![0]).is_err());
+
+        assert!(matches!(
+            FileStream::new(dir.path().join("does_not_exist.bin").to_str().unwrap(), "r"),
+            Err(StreamError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use std::io::{Read, SeekFrom};
+
+use crate::{C2paError, Result};
+
+/// The format string used to request format detection
+pub const AUTO_FORMAT: &str = "auto";
+
+/// The number of bytes inspected when sniffing a format
+const SNIFF_LEN: u64 = 1024;
+
+/// Returns true if the format should be detected from the stream content
+pub(crate) fn is_auto(format: &str) -> bool {
+    format.is_empty() || format.eq_ignore_ascii_case(AUTO_FORMAT)
+}
+
+/// Returns the format to use for a stream, detecting it if requested
+/// # Arguments
+/// * `format` - the format given by the caller, may be empty or "auto"
+/// * `reader` - the stream to detect the format from
+/// # Returns
+/// * `Result<String>` - the format given or the detected mime type
+///
+pub(crate) fn resolve_format(format: &str, reader: &mut dyn c2pa::CAIRead) -> Result<String> {
+    if is_auto(format) {
+        format_from_reader(reader)
+    } else {
+        Ok(format.to_string())
+    }
+}
+
+/// Detects the format of an asset by inspecting the leading bytes of a stream
+///
+/// The stream position is restored before returning
+/// # Arguments
+/// * `reader` - the stream to inspect
+/// # Returns
+/// * `Result<String>` - the mime type of the asset or a NotSupported error
+///
+pub fn format_from_reader(reader: &mut dyn c2pa::CAIRead) -> Result<String> {
+    let pos = reader.stream_position().map_err(C2paError::from)?;
+    reader.rewind().map_err(C2paError::from)?;
+    let mut header = Vec::with_capacity(SNIFF_LEN as usize);
+    let result = Read::take(&mut *reader, SNIFF_LEN).read_to_end(&mut header);
+    reader.seek(SeekFrom::Start(pos)).map_err(C2paError::from)?;
+    result.map_err(C2paError::from)?;
+
+    format_from_bytes(&header)
+        .map(|f| f.to_string())
+        .ok_or_else(|| C2paError::NotSupported("unable to detect format".to_string()))
+}
+
+// Matches the magic bytes of the supported asset formats
+fn format_from_bytes(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some("image/png"),
+        // DNG files are TIFF files and are handled by the same asset handler
+        [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => Some("image/tiff"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("audio/wav"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => Some("video/avi"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b0, b1, b2, b3, ..] => {
+            Some(format_from_brand(&[*b0, *b1, *b2, *b3]))
+        }
+        [_, _, _, _, b'j', b'u', b'm', b'b', ..] => Some("application/c2pa"),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        [b'I', b'D', b'3', ..] => Some("audio/mpeg"),
+        [0xff, b1, ..] if b1 & 0xe0 == 0xe0 => Some("audio/mpeg"),
+        _ if is_svg(header) => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+// Maps the major brand of an ISO BMFF ftyp box to a format
+fn format_from_brand(brand: &[u8; 4]) -> &'static str {
+    match brand {
+        b"avif" | b"avis" => "image/avif",
+        b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => "image/heic",
+        b"mif1" | b"msf1" => "image/heif",
+        b"M4A " | b"M4B " => "audio/mp4",
+        // there is no registered mime type for QuickTime in the c2pa sdk
+        b"qt  " => "mov",
+        _ => "video/mp4",
+    }
+}
+
+// SVG is text, so look for an svg element after an optional XML prolog
+fn is_svg(header: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(header.strip_prefix(b"\xef\xbb\xbf").unwrap_or(header));
+    let text = text.trim_start();
+    (text.starts_with("<?xml") || text.starts_with("<!--") || text.starts_with("<svg"))
+        && text.contains("<svg")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    const IMAGE: &[u8] = include_bytes!("../tests/fixtures/A.jpg");
+
+    #[test]
+    fn test_format_from_reader() {
+        let mut stream = Cursor::new(IMAGE.to_vec());
+        stream.set_position(10);
+        let format = format_from_reader(&mut stream).unwrap();
+        assert_eq!(format, "image/jpeg");
+        assert_eq!(stream.position(), 10);
+    }
+
+    #[test]
+    fn test_format_from_bytes() {
+        let formats: [(&[u8], &str); 12] = [
+            (b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR", "image/png"),
+            (b"II*\0\x08\0\0\0", "image/tiff"),
+            (b"MM\0*\0\0\0\x08", "image/tiff"),
+            (b"RIFF\0\0\0\0WEBPVP8 ", "image/webp"),
+            (b"RIFF\0\0\0\0WAVEfmt ", "audio/wav"),
+            (b"\0\0\0\x18ftypheic\0\0\0\0", "image/heic"),
+            (b"\0\0\0\x18ftypavif\0\0\0\0", "image/avif"),
+            (b"\0\0\0\x18ftypisom\0\0\0\0", "video/mp4"),
+            (b"\0\0\0\x14ftypqt  \0\0\0\0", "mov"),
+            (b"ID3\x04\0\0\0\0\0\0", "audio/mpeg"),
+            (b"%PDF-1.7\n", "application/pdf"),
+            (
+                b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+                "image/svg+xml",
+            ),
+        ];
+        for (header, format) in formats {
+            assert_eq!(format_from_bytes(header), Some(format));
+        }
+        assert_eq!(format_from_bytes(b"not a known format"), None);
+    }
+}
@@ -15,9 +15,87 @@ use std::sync::RwLock;
 
 use c2pa::Ingredient;
 
-use crate::{C2paError, Result, Stream, StreamAdapter};
+use crate::{format::resolve_format, C2paError, NativeStream, Result, Stream, StreamAdapter};
 
-pub struct IngredientBuilderSettings {}
+/// The relationship of an ingredient to the asset it is added to
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Relationship {
+    ParentOf,
+    #[default]
+    ComponentOf,
+    InputTo,
+}
+
+impl Relationship {
+    // The name used for the relationship in ingredient json
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ParentOf => "parentOf",
+            Self::ComponentOf => "componentOf",
+            Self::InputTo => "inputTo",
+        }
+    }
+}
+
+// The sdk relationship type is not public, so it is set from its json name
+fn set_relationship(ingredient: &mut Ingredient, relationship: Relationship) {
+    if let Ok(relationship) = serde_json::from_value(relationship.as_str().into()) {
+        ingredient.set_relationship(relationship);
+    }
+}
+
+/// Reads an ingredient from an asset
+///
+/// This captures the manifest store and thumbnail of the asset,
+/// and sets the hash to the sha256 of the whole asset
+pub(crate) fn ingredient_from_reader(
+    format: &str,
+    reader: &mut dyn c2pa::CAIRead,
+) -> Result<Ingredient> {
+    let format = resolve_format(format, reader)?;
+    let mut ingredient = Ingredient::from_stream(&format, reader).map_err(C2paError::from)?;
+    reader.rewind().map_err(C2paError::from)?;
+    let hash = c2pa::hash_stream_by_alg("sha256", reader, None, true)?;
+    ingredient.set_hash(hash.iter().map(|b| format!("{b:02x}")).collect::<String>());
+    Ok(ingredient)
+}
+
+/// Copies an ingredient and its resources
+///
+/// The fields of an ingredient definition, if given, replace those of the ingredient
+pub(crate) fn copy_ingredient(ingredient: &Ingredient, json: Option<&str>) -> Result<Ingredient> {
+    let json_error = |e: serde_json::Error| C2paError::Json(e.to_string());
+    let mut value = serde_json::to_value(ingredient).map_err(json_error)?;
+    if let Some(json) = json {
+        let definition: serde_json::Value = serde_json::from_str(json).map_err(json_error)?;
+        match (value.as_object_mut(), definition) {
+            (Some(fields), serde_json::Value::Object(definition)) => fields.extend(definition),
+            _ => {
+                return Err(C2paError::Json(
+                    "ingredient definition must be an object".to_string(),
+                ))
+            }
+        }
+    }
+    let mut copy: Ingredient = serde_json::from_value(value).map_err(json_error)?;
+    for (id, data) in ingredient.resources().resources() {
+        copy.resources_mut().add(id.as_str(), data.clone())?;
+    }
+    Ok(copy)
+}
+
+/// Settings for an IngredientBuilder
+///
+/// The title and relationship are also applied to ingredients read from a stream,
+/// the format and instance id are then taken from the asset
+#[derive(Clone, Debug)]
+pub struct IngredientBuilderSettings {
+    pub title: String,
+    pub format: String,
+    pub instance_id: String,
+    pub relationship: Relationship,
+}
 
 pub struct IngredientBuilder {
     pub settings: IngredientBuilderSettings,
@@ -25,25 +103,78 @@ pub struct IngredientBuilder {
 }
 
 impl IngredientBuilder {
-    pub fn new(settings: IngredientBuilderSettings) -> Self {
+    pub fn new(settings: &IngredientBuilderSettings) -> Self {
+        let mut ingredient = Ingredient::new(
+            settings.title.as_str(),
+            settings.format.as_str(),
+            settings.instance_id.as_str(),
+        );
+        set_relationship(&mut ingredient, settings.relationship);
         Self {
-            settings,
-            ingredient: RwLock::new(Ingredient::new("test", "image/jpeg", "instance_id")),
+            settings: settings.clone(),
+            ingredient: RwLock::new(ingredient),
         }
     }
 
-    // pub fn from_json(&self, json: &str) -> Result<()> {
-    //     let ingredient = Ingredient::from_json(json).map_err(C2paError::Sdk)?;
-    //     if let Ok(mut m) = self.ingredient.try_write() {
-    //         *m = ingredient;
-    //     } else {
-    //         return Err(C2paError::RwLock);
-    //     };
-    //     Ok(())
-    // }
+    /// Replaces the ingredient with one from an ingredient definition
+    ///
+    /// Resources of the current ingredient are kept when the definition still refers to them,
+    /// so the json of a read ingredient can be edited and set again
+    /// # Arguments
+    /// * `json` - the json representation of an ingredient
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn from_json(&self, json: &str) -> Result<()> {
+        let mut ingredient: Ingredient =
+            serde_json::from_str(json).map_err(|e| C2paError::Json(e.to_string()))?;
+        if let Ok(mut i) = self.ingredient.try_write() {
+            let ids: Vec<String> = [
+                ingredient.thumbnail_ref(),
+                ingredient.manifest_data_ref(),
+                ingredient.data_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|r| r.identifier.clone())
+            .collect();
+            for id in ids {
+                if let Ok(data) = i.resources().get(&id) {
+                    let data = data.into_owned();
+                    ingredient.resources_mut().add(id, data)?;
+                }
+            }
+            *i = ingredient;
+        } else {
+            return Err(C2paError::RwLock);
+        };
+        Ok(())
+    }
 
+    /// Reads an ingredient from a stream
+    /// # Arguments
+    /// * `format` - the format of the asset, empty or "auto" to detect it
+    /// * `stream` - the stream to read from
+    /// # Returns
+    /// * `Result<String>` - the json representation of the ingredient or an error
+    ///
+    pub fn read_stream(&self, format: &str, stream: &dyn Stream) -> Result<String> {
+        let mut stream = StreamAdapter::from(stream);
+        self.read(format, &mut stream)
+    }
+
+    /// Reads an ingredient from a native stream, without calling back into the host
+    pub fn read_native_stream(&self, format: &str, stream: &NativeStream) -> Result<String> {
+        self.read_stream(format, stream)
+    }
+
+    /// Reads an ingredient from a stream
+    ///
+    /// The ingredient captures the manifest store, thumbnail and hash of the asset
     pub fn read(&self, format: &str, stream: &mut dyn c2pa::CAIRead) -> Result<String> {
-        let ingredient = Ingredient::from_stream(format, stream).map_err(C2paError::from)?;
+        let mut ingredient = ingredient_from_reader(format, stream)?;
+        ingredient.set_title(self.settings.title.as_str());
+        set_relationship(&mut ingredient, self.settings.relationship);
         if let Ok(mut i) = self.ingredient.try_write() {
             let json = ingredient.to_string();
             *i = ingredient;
@@ -53,6 +184,15 @@ impl IngredientBuilder {
         }
     }
 
+    // Returns a copy of the ingredient, with its resources, to add to a manifest
+    pub(crate) fn to_ingredient(&self) -> Result<Ingredient> {
+        if let Ok(i) = self.ingredient.try_read() {
+            copy_ingredient(&i, None)
+        } else {
+            Err(C2paError::RwLock)
+        }
+    }
+
     pub fn json(&self) -> Result<String> {
         if let Ok(i) = self.ingredient.try_read() {
             Ok(i.to_string())
@@ -81,7 +221,9 @@ impl IngredientBuilder {
         if let Ok(ingredient) = self.ingredient.try_read() {
             match ingredient.resources().get(id) {
                 Ok(bytes) => {
-                    stream.write_all(&bytes).map_err(C2paError::from)?;
+                    stream.write_all(&bytes)?;
+                    // report a failed write here rather than lose it when a buffered stream is dropped
+                    stream.flush()?;
                     Ok(())
                 }
                 Err(e) => Err(C2paError::from(e)),
@@ -91,3 +233,88 @@ impl IngredientBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_stream::TestStream;
+
+    const IMAGE: &[u8] = include_bytes!("../tests/fixtures/C.jpg");
+
+    fn settings() -> IngredientBuilderSettings {
+        IngredientBuilderSettings {
+            title: "C.jpg".to_string(),
+            format: "image/jpeg".to_string(),
+            instance_id: "xmp:iid:test".to_string(),
+            relationship: Relationship::ParentOf,
+        }
+    }
+
+    #[test]
+    fn test_ingredient_builder_settings() {
+        let builder = IngredientBuilder::new(&settings());
+        let json = builder.json().unwrap();
+        assert!(json.contains("\"title\": \"C.jpg\""));
+        assert!(json.contains("\"instance_id\": \"xmp:iid:test\""));
+        assert!(json.contains("\"relationship\": \"parentOf\""));
+    }
+
+    #[test]
+    fn test_ingredient_builder_from_json() {
+        let builder = IngredientBuilder::new(&settings());
+        builder
+            .read_stream("image/jpeg", &TestStream::from_memory(IMAGE.to_vec()))
+            .unwrap();
+        let json = builder.json().unwrap();
+
+        // round trip the definition through a new builder
+        let other = IngredientBuilder::new(&settings());
+        other.from_json(&json).unwrap();
+        assert_eq!(other.json().unwrap(), json);
+
+        // the resources the definition refers to are kept
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let id = value["manifest_data"]["identifier"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let manifest_data = builder.resource(&id).unwrap();
+        builder.from_json(&json).unwrap();
+        assert_eq!(builder.resource(&id).unwrap(), manifest_data);
+        let output = TestStream::new();
+        builder.resource_write_stream(&id, &output).unwrap();
+        let len = output.seek_stream(0, crate::SeekMode::End).unwrap();
+        assert_eq!(len, manifest_data.len() as u64);
+
+        // but not those it no longer refers to
+        let mut value = value;
+        value.as_object_mut().unwrap().remove("manifest_data");
+        builder.from_json(&value.to_string()).unwrap();
+        assert!(builder.resource(&id).is_err());
+
+        assert!(matches!(other.from_json("{"), Err(C2paError::Json(_))));
+    }
+
+    #[test]
+    fn test_ingredient_builder_read_stream() {
+        let builder = IngredientBuilder::new(&settings());
+        let stream = TestStream::from_memory(IMAGE.to_vec());
+        let json = builder.read_stream("auto", &stream).unwrap();
+        assert!(json.contains("\"format\": \"image/jpeg\""));
+        assert!(json.contains("\"title\": \"C.jpg\""));
+        assert!(json.contains("\"relationship\": \"parentOf\""));
+        assert!(json.contains("\"hash\": \""));
+        assert_eq!(json, builder.json().unwrap());
+
+        // the manifest store of the ingredient is available as a resource
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let id = value["manifest_data"]["identifier"].as_str().unwrap();
+        let output = TestStream::new();
+        builder.resource_write_stream(id, &output).unwrap();
+        assert_eq!(output.flush_count(), 1);
+        let manifest_data = builder.resource(id).unwrap();
+        assert!(!manifest_data.is_empty());
+        let len = output.seek_stream(0, crate::SeekMode::End).unwrap();
+        assert_eq!(len, manifest_data.len() as u64);
+    }
+}
@@ -16,18 +16,31 @@ use c2pa::jumbf_io::get_supported_types;
 mod c_api;
 /// This module exports a C2PA library
 mod error;
+mod file_stream;
+mod format;
 mod ingredient_builder;
 mod manifest_builder;
 mod manifest_store_reader;
+mod memory_stream;
+mod native_stream;
 mod signer;
+mod spool_stream;
 mod stream;
+mod xmp;
 
 pub use c_api::C2paStream;
-pub use error::{Error as C2paError, Result};
-pub use ingredient_builder::IngredientBuilder;
+pub use error::{Error as C2paError, ErrorCode as C2paErrorCode, Result};
+pub use file_stream::FileStream;
+pub use format::{format_from_reader, AUTO_FORMAT};
+pub use ingredient_builder::{IngredientBuilder, IngredientBuilderSettings, Relationship};
 pub use manifest_builder::{ManifestBuilder, ManifestBuilderSettings};
-pub use manifest_store_reader::ManifestStoreReader;
+pub use manifest_store_reader::{
+    ManifestFetcher, ManifestStoreReader, ManifestStoreReaderSettings,
+};
+pub use memory_stream::MemoryStream;
+pub use native_stream::NativeStream;
 pub use signer::{C2paSigner, SignerCallback, SignerConfig};
+pub use spool_stream::SpoolStream;
 pub use stream::{SeekMode, Stream, StreamAdapter, StreamError, StreamResult};
 
 #[cfg(test)]
@@ -59,3 +72,14 @@ pub fn supported_extensions() -> Vec<String> {
     formats.sort();
     formats
 }
+
+/// Detects the format of an asset from the content of a stream
+/// # Arguments
+/// * `stream` - the stream to inspect
+/// # Returns
+/// * `Result<String>` - the mime type of the asset or an error
+///
+pub fn detect_format(stream: &dyn Stream) -> Result<String> {
+    let mut stream = StreamAdapter::from(stream);
+    format_from_reader(&mut stream)
+}
@@ -11,17 +11,25 @@
 // specific language governing permissions and limitations under
 // each license.
 
-use std::{collections::HashMap, sync::RwLock};
+use std::{
+    collections::HashMap,
+    io::{Cursor, Seek, Write},
+    sync::RwLock,
+};
 
-use c2pa::{CAIRead, CAIReadWrite, Manifest, Signer};
+use c2pa::{assertions::DataHash, CAIRead, CAIReadWrite, Manifest, Signer};
 
 use crate::{
+    ingredient_builder::{copy_ingredient, ingredient_from_reader},
     stream::{Stream, StreamAdapter},
-    C2paError, C2paSigner, Result,
+    C2paError, C2paSigner, IngredientBuilder, NativeStream, Result,
 };
 
 pub struct ManifestBuilderSettings {
     pub generator: String,
+    /// If set, signing writes a manifest store (.c2pa) to the output
+    /// and leaves the asset unmodified
+    pub sidecar: bool,
 }
 
 trait StreamResolver: Send + Sync {
@@ -38,8 +46,16 @@ impl StreamResolver for StreamTable {
     }
 }
 
+// A remote manifest url and whether the manifest is only stored remotely
+struct RemoteUrl {
+    url: String,
+    remote_only: bool,
+}
+
 pub struct ManifestBuilder {
     manifest: RwLock<Manifest>,
+    sidecar: bool,
+    remote_url: RwLock<Option<RemoteUrl>>,
     _resolvers: Vec<Box<dyn StreamResolver>>,
 }
 
@@ -47,6 +63,8 @@ impl ManifestBuilder {
     pub fn new(settings: &ManifestBuilderSettings) -> Self {
         Self {
             manifest: RwLock::new(Manifest::new(settings.generator.clone())),
+            sidecar: settings.sidecar,
+            remote_url: RwLock::new(None),
             _resolvers: Vec::new(),
         }
     }
@@ -55,6 +73,10 @@ impl ManifestBuilder {
         self.manifest.try_write().map_err(|_| C2paError::RwLock)
     }
 
+    fn unlock_read(&self) -> Result<std::sync::RwLockReadGuard<'_, Manifest>> {
+        self.manifest.try_read().map_err(|_| C2paError::RwLock)
+    }
+
     pub fn from_json(&self, json: &str) -> Result<()> {
         *self.unlock_write()? = c2pa::Manifest::from_json(json).map_err(C2paError::from)?;
         Ok(())
@@ -69,8 +91,30 @@ impl ManifestBuilder {
         self
     }
 
-    fn _set_remote_url(&mut self, _url: &str, _remote_only: bool) -> &mut Self {
-        self
+    /// Sets a url where the manifest store will be available
+    ///
+    /// The url is written to the XMP provenance of the signed asset.
+    /// If `remote_only` is set the manifest is not embedded, and the manifest store
+    /// returned from signing must be uploaded to the url.
+    /// Only JPEG is currently supported: signing other formats fails with NotSupported,
+    /// and the asset is held in memory while the reference is written to its XMP
+    /// # Arguments
+    /// * `url` - the url of the remote manifest store
+    /// * `remote_only` - if true, only the remote reference is written to the asset
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn set_remote_url(&self, url: &str, remote_only: bool) -> Result<()> {
+        if self.sidecar {
+            return Err(C2paError::NotSupported(
+                "remote urls cannot be used in sidecar mode".to_string(),
+            ));
+        }
+        *self.remote_url.try_write().map_err(|_| C2paError::RwLock)? = Some(RemoteUrl {
+            url: url.to_string(),
+            remote_only,
+        });
+        Ok(())
     }
 
     pub fn add_resource(&mut self, id: &str, resource: &[u8]) -> Result<&Self> {
@@ -78,10 +122,78 @@ impl ManifestBuilder {
         Ok(self)
     }
 
-    pub fn add_resource_stream(&mut self, _id: &str, _stream: Box<dyn Stream>) -> Result<&Self> {
-        // let buf = _stream.read_stream(65565).map_err(C2paError::from)?;
-        // self.unlock_write()?.resources_mut().add(_id, _stream);
-        Ok(self)
+    /// Adds a resource to the manifest from a stream
+    ///
+    /// The resource is stored as raw bytes under its identifier.
+    /// Its mime type is not taken from the stream, it comes from the manifest
+    /// definition that references the identifier, such as the `format` of a
+    /// `thumbnail` or `icon`, so that entry must be set in the JSON
+    /// # Arguments
+    /// * `id` - the identifier used to reference the resource in the manifest definition
+    /// * `stream` - the stream to read the resource from
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn add_resource_stream(&self, id: &str, stream: &dyn Stream) -> Result<()> {
+        let mut stream = StreamAdapter::from(stream);
+        self.add_resource_reader(id, &mut stream)
+    }
+
+    /// Adds a resource to the manifest from a reader
+    ///
+    /// The reader is read from the start to the end, and its size is what was read.
+    /// As with add_resource_stream, the mime type comes from the manifest definition
+    pub fn add_resource_reader(&self, id: &str, reader: &mut dyn CAIRead) -> Result<()> {
+        reader.rewind().map_err(C2paError::from)?;
+        let mut resource = Vec::new();
+        reader.read_to_end(&mut resource).map_err(C2paError::from)?;
+        self.unlock_write()?.resources_mut().add(id, resource)?;
+        Ok(())
+    }
+
+    /// Adds an ingredient read by an IngredientBuilder to the manifest
+    /// # Arguments
+    /// * `ingredient` - the IngredientBuilder holding the ingredient
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn add_ingredient(&self, ingredient: &IngredientBuilder) -> Result<()> {
+        let ingredient = ingredient.to_ingredient()?;
+        self.unlock_write()?.add_ingredient(ingredient);
+        Ok(())
+    }
+
+    /// Adds an ingredient to the manifest from a stream
+    /// # Arguments
+    /// * `json` - an ingredient definition, with fields that replace those read from the stream
+    /// * `format` - the format of the ingredient, empty or "auto" to detect it
+    /// * `stream` - the stream to read the ingredient from
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn add_ingredient_stream(
+        &self,
+        json: &str,
+        format: &str,
+        stream: &dyn Stream,
+    ) -> Result<()> {
+        let mut stream = StreamAdapter::from(stream);
+        self.add_ingredient_reader(json, format, &mut stream)
+    }
+
+    /// Adds an ingredient to the manifest from a reader
+    ///
+    /// The ingredient captures the manifest store, thumbnail and hash of the asset
+    pub fn add_ingredient_reader(
+        &self,
+        json: &str,
+        format: &str,
+        reader: &mut dyn CAIRead,
+    ) -> Result<()> {
+        let ingredient = ingredient_from_reader(format, reader)?;
+        let ingredient = copy_ingredient(&ingredient, Some(json))?;
+        self.unlock_write()?.add_ingredient(ingredient);
+        Ok(())
     }
 
     pub fn sign_stream(
@@ -95,25 +207,154 @@ impl ManifestBuilder {
         self.sign(signer, &mut input, &mut output)
     }
 
+    /// Signs the manifest, writing the signed asset sequentially to a write-only output
+    ///
+    /// Only write_stream and flush_stream are called on the output,
+    /// so it can be a pipe, a socket or a response body.
+    /// The signed asset is staged in a temporary file, and the input must be seekable
+    pub fn sign_stream_sequential(
+        &self,
+        signer: &C2paSigner,
+        input: &dyn Stream,
+        output: &dyn Stream,
+    ) -> Result<Vec<u8>> {
+        let mut input = StreamAdapter::from(input);
+        let mut output = StreamAdapter::from(output);
+        self.sign_sequential(signer, &mut input, &mut output)
+    }
+
+    /// Signs the manifest with native streams, without calling back into the host
+    ///
+    /// If the output is a memory stream, the signed asset is then available from its data
+    pub fn sign_native_stream(
+        &self,
+        signer: &C2paSigner,
+        input: &NativeStream,
+        output: &NativeStream,
+    ) -> Result<Vec<u8>> {
+        self.sign_stream(signer, input, output)
+    }
+
+    /// Signs the manifest, embedding it into the output
+    ///
+    /// In sidecar mode the output receives the manifest store (.c2pa)
+    /// instead of the asset, and the asset itself is not modified.
+    /// If a remote only url is set, the output receives the asset with only
+    /// the remote reference, and the returned manifest store must be uploaded
+    /// # Arguments
+    /// * `signer` - the signer to use
+    /// * `input` - the asset to sign
+    /// * `output` - the stream to write the signed asset or manifest store to
+    /// # Returns
+    /// * `Result<Vec<u8>>` - the bytes of the manifest store
+    ///
     pub fn sign(
         &self,
         signer: &dyn Signer,
         input: &mut dyn CAIRead,
         output: &mut dyn CAIReadWrite,
     ) -> Result<Vec<u8>> {
-        let mut manifest = self.unlock_write()?;
+        // signing adds bindings to the asset, so each signature works on its own copy
+        let mut manifest = copy_manifest(&*self.unlock_read()?)?;
         let format = manifest.format().to_string();
+        let manifest_bytes = if self.sidecar {
+            let manifest_bytes = Self::sign_sidecar(&mut manifest, &format, signer, input)?;
+            output.write_all(&manifest_bytes).map_err(C2paError::from)?;
+            manifest_bytes
+        } else {
+            let remote_url = self.remote_url.try_read().map_err(|_| C2paError::RwLock)?;
+            match remote_url.as_ref() {
+                Some(remote) => {
+                    // only JPEG can hold the reference, so fail before buffering the asset
+                    crate::xmp::check_remote_reference_format(&format)?;
+                    manifest.set_embedded_manifest_with_remote_ref(&remote.url);
+                    let mut asset = Cursor::new(Vec::new());
+                    crate::xmp::embed_remote_reference(&format, &remote.url, input, &mut asset)?;
+                    asset.rewind().map_err(C2paError::from)?;
+                    if remote.remote_only {
+                        let manifest_bytes =
+                            Self::sign_sidecar(&mut manifest, &format, signer, &mut asset)?;
+                        output.write_all(asset.get_ref()).map_err(C2paError::from)?;
+                        manifest_bytes
+                    } else {
+                        manifest.embed_to_stream(&format, &mut asset, output, signer)?
+                    }
+                }
+                None => manifest.embed_to_stream(&format, input, output, signer)?,
+            }
+        };
+        // buffered outputs must be flushed for the signed asset to be complete
+        output.flush().map_err(C2paError::from)?;
+        Ok(manifest_bytes)
+    }
+
+    /// Signs the manifest, writing the signed asset strictly sequentially
+    ///
+    /// Embedding a manifest seeks back into the output to patch in hashes and the signature,
+    /// so the signed asset is staged in a temporary file and then written from start to end.
+    /// The input must still be seekable, a forward-only input can be wrapped in a SpoolStream
+    /// # Arguments
+    /// * `signer` - the signer to use
+    /// * `input` - the asset to sign
+    /// * `output` - the writer to write the signed asset or manifest store to
+    /// # Returns
+    /// * `Result<Vec<u8>>` - the bytes of the manifest store
+    ///
+    pub fn sign_sequential(
+        &self,
+        signer: &dyn Signer,
+        input: &mut dyn CAIRead,
+        output: &mut dyn Write,
+    ) -> Result<Vec<u8>> {
+        // the temporary file is deleted when it is dropped
+        let mut staged = tempfile::tempfile().map_err(C2paError::from)?;
+        let manifest_bytes = self.sign(signer, input, &mut staged)?;
+        staged.rewind().map_err(C2paError::from)?;
+        std::io::copy(&mut staged, output).map_err(C2paError::from)?;
+        output.flush().map_err(C2paError::from)?;
+        Ok(manifest_bytes)
+    }
+
+    // Generates a manifest store that binds to the unmodified asset.
+    // The data hash covers the whole asset, so the embedded copy is discarded
+    fn sign_sidecar(
+        manifest: &mut Manifest,
+        format: &str,
+        signer: &dyn Signer,
+        input: &mut dyn CAIRead,
+    ) -> Result<Vec<u8>> {
+        let mut data_hash = DataHash::new("jumbf manifest", "sha256");
+        input.rewind().map_err(C2paError::from)?;
+        data_hash.gen_hash_from_stream(input)?;
+        manifest.add_assertion(&data_hash)?;
+        let mut scratch = Cursor::new(Vec::new());
         manifest
-            .embed_to_stream(&format, input, output, signer)
+            .embed_to_stream(format, input, &mut scratch, signer)
             .map_err(C2paError::from)
     }
 }
 
+/// Copies a manifest definition with its resources and ingredients
+fn copy_manifest(manifest: &Manifest) -> Result<Manifest> {
+    let json_error = |e: serde_json::Error| C2paError::Json(e.to_string());
+    let mut value = serde_json::to_value(manifest).map_err(json_error)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("ingredients");
+    }
+    let mut copy: Manifest = serde_json::from_value(value).map_err(json_error)?;
+    for (id, data) in manifest.resources().resources() {
+        copy.resources_mut().add(id.as_str(), data.clone())?;
+    }
+    for ingredient in manifest.ingredients() {
+        copy.add_ingredient(copy_ingredient(ingredient, None)?);
+    }
+    Ok(copy)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{signer::C2paSigner, test_signer::TestSigner, test_stream::TestStream, SeekMode};
-    use std::io::Seek;
 
     const MANIFEST_JSON: &str = r#"
     {
@@ -128,6 +369,7 @@ mod tests {
     "#;
 
     const IMAGE: &'static [u8] = include_bytes!("../tests/fixtures/A.jpg");
+    const INGREDIENT: &[u8] = include_bytes!("../tests/fixtures/C.jpg");
     const CERTS: &'static [u8] = include_bytes!("../tests/fixtures/ps256.pub");
     const P_KEY: &'static [u8] = include_bytes!("../tests/fixtures/ps256.pem");
 
@@ -135,12 +377,15 @@ mod tests {
     fn test_manifest_builder() {
         let settings = ManifestBuilderSettings {
             generator: "test".to_string(),
+            sidecar: false,
         };
         let mut builder = ManifestBuilder::new(&settings);
         builder
             .from_json(MANIFEST_JSON)
             .expect("Failed to load manifest Json");
-        builder.add_resource("thumbnail", &IMAGE.to_vec()).expect("Failed to add thumbnail stream");
+        builder
+            .add_resource("thumbnail", &IMAGE.to_vec())
+            .expect("Failed to add thumbnail stream");
         let mut input = TestStream::from_memory(IMAGE.to_vec());
         let mut input = StreamAdapter::from_stream_mut(&mut input);
         //let mut output = Cursor::new(Vec::new());
@@ -160,12 +405,15 @@ mod tests {
     fn test_manifest_builder_with_stream() {
         let settings = ManifestBuilderSettings {
             generator: "test".to_string(),
+            sidecar: false,
         };
         let mut builder = ManifestBuilder::new(&settings);
         builder
             .from_json(MANIFEST_JSON)
             .expect("Failed to load manifest Json");
-        builder.add_resource("thumbnail", &IMAGE.to_vec()).expect("Failed to add thumbnail stream");
+        builder
+            .add_resource("thumbnail", &IMAGE.to_vec())
+            .expect("Failed to add thumbnail stream");
         let mut input = TestStream::from_memory(IMAGE.to_vec());
         let mut output = TestStream::new();
         let test_signer = Box::new(TestSigner::new());
@@ -177,5 +425,416 @@ mod tests {
             .expect("Failed to sign");
         let len = output.seek_stream(0, SeekMode::End).unwrap();
         assert_eq!(len, 151443);
+        assert_eq!(output.flush_count(), 1);
+    }
+
+    // Counts the stream calls made while signing an asset
+    fn sign_calls(buffer_size: usize) -> usize {
+        let mut builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource("thumbnail", IMAGE)
+            .expect("Failed to add thumbnail");
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = TestStream::new();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        let mut input_ref = StreamAdapter::with_buffer_size(&input, buffer_size);
+        let mut output_ref = StreamAdapter::with_buffer_size(&output, buffer_size);
+        builder
+            .sign(&signer, &mut input_ref, &mut output_ref)
+            .expect("Failed to sign");
+        drop((input_ref, output_ref));
+        let calls = input.call_count() + output.call_count();
+        assert_eq!(output.seek_stream(0, SeekMode::End).unwrap(), 151443);
+        calls
+    }
+
+    #[test]
+    fn bench_sign_stream_calls() {
+        let unbuffered = sign_calls(0);
+        let buffered = sign_calls(StreamAdapter::DEFAULT_BUFFER_SIZE);
+        assert!(buffered < unbuffered);
+    }
+
+    // A stream that can only be written to, like a pipe
+    struct WriteOnlyStream(TestStream);
+
+    impl Stream for WriteOnlyStream {
+        fn read_stream(&self, _length: u64) -> crate::StreamResult<Vec<u8>> {
+            Err(crate::StreamError::Other {
+                reason: "read from a write-only stream".to_string(),
+            })
+        }
+
+        fn seek_stream(&self, _pos: i64, _mode: SeekMode) -> crate::StreamResult<u64> {
+            Err(crate::StreamError::Other {
+                reason: "seek in a write-only stream".to_string(),
+            })
+        }
+
+        fn write_stream(&self, data: Vec<u8>) -> crate::StreamResult<u64> {
+            self.0.write_stream(data)
+        }
+
+        fn flush_stream(&self) -> crate::StreamResult<()> {
+            self.0.flush_stream()
+        }
+    }
+
+    #[test]
+    fn test_manifest_builder_sign_sequential() {
+        let mut builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource("thumbnail", IMAGE)
+            .expect("Failed to add thumbnail");
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = WriteOnlyStream(TestStream::new());
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        assert!(builder.sign_stream(&signer, &input, &output).is_err());
+
+        let output = WriteOnlyStream(TestStream::new());
+        builder
+            .sign_stream_sequential(&signer, &input, &output)
+            .expect("Failed to sign");
+        assert_eq!(output.0.flush_count(), 1);
+        output.0.seek_stream(0, SeekMode::Start).unwrap();
+        let json = crate::ManifestStoreReader::new()
+            .read_stream("image/jpeg", &output.0)
+            .expect("Failed to read signed asset");
+        assert!(json.contains("test_title"));
+    }
+
+    #[test]
+    fn test_manifest_builder_file_stream() {
+        let builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("test_file_stream.jpg");
+        let path = path.to_str().unwrap();
+        let input =
+            NativeStream::open_file("tests/fixtures/A.jpg", "rb").expect("Failed to open input");
+        let output = NativeStream::open_file(path, "w+b").expect("Failed to open output");
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        builder
+            .sign_native_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+
+        let reader = crate::ManifestStoreReader::new();
+        let input = NativeStream::open_file(path, "rb").expect("Failed to open signed asset");
+        let json = reader
+            .read_native_stream("image/jpeg", &input)
+            .expect("Failed to read signed asset");
+        assert!(json.contains("test_title"));
+    }
+
+    #[test]
+    fn test_manifest_builder_memory_stream() {
+        let builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &NativeStream::from_bytes(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+        let image = std::fs::read("tests/fixtures/A.jpg").expect("Failed to read input");
+        let input = NativeStream::from_bytes(image);
+        let output = NativeStream::memory();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        builder
+            .sign_native_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+
+        let signed = NativeStream::from_bytes(output.data().expect("Failed to get output"));
+        let json = crate::ManifestStoreReader::new()
+            .read_native_stream("image/jpeg", &signed)
+            .expect("Failed to read signed asset");
+        assert!(json.contains("test_title"));
+    }
+
+    #[test]
+    fn test_manifest_builder_sidecar() {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: true,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = TestStream::new();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        let manifest_bytes = builder
+            .sign_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+        let len = output.seek_stream(0, SeekMode::End).unwrap();
+        assert_eq!(len, manifest_bytes.len() as u64);
+
+        // the sidecar must validate against the unmodified asset
+        let reader = crate::ManifestStoreReader::new();
+        let json = reader
+            .read_with_manifest_stream("image/jpeg", &output, &input)
+            .expect("Failed to read sidecar");
+        assert!(json.contains("\"title\": \"test_title\""));
+        assert!(!json.contains("\"validation_status\":"));
+    }
+
+    #[test]
+    fn test_manifest_builder_sign_twice() {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: true,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+
+        // each sidecar only binds to the asset it was signed with
+        for asset in [IMAGE, INGREDIENT] {
+            let input = TestStream::from_memory(asset.to_vec());
+            let output = TestStream::new();
+            builder
+                .sign_stream(&signer, &input, &output)
+                .expect("Failed to sign");
+            let json = crate::ManifestStoreReader::new()
+                .read_with_manifest_stream("image/jpeg", &output, &input)
+                .expect("Failed to read sidecar");
+            assert!(json.contains("\"title\": \"test_title\""));
+            assert!(!json.contains("\"validation_status\":"));
+        }
+    }
+
+    fn remote_builder(remote_only: bool) -> ManifestBuilder {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+        builder
+            .set_remote_url(REMOTE_URL, remote_only)
+            .expect("Failed to set remote url");
+        builder
+    }
+
+    const REMOTE_URL: &str = "https://example.com/manifest.c2pa";
+
+    #[test]
+    fn test_manifest_builder_remote_only() {
+        let builder = remote_builder(true);
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = TestStream::new();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        let manifest_bytes = builder
+            .sign_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+
+        // the asset only has a reference to the remote manifest
+        let reader = crate::ManifestStoreReader::new();
+        let result = reader.read_stream("image/jpeg", &output);
+        assert!(matches!(result, Err(C2paError::RemoteManifest(_))));
+
+        // the returned manifest store validates against the output asset
+        let manifest_stream = TestStream::from_memory(manifest_bytes.clone());
+        let json = reader
+            .read_with_manifest_stream("image/jpeg", &manifest_stream, &output)
+            .expect("Failed to read remote manifest");
+        assert!(json.contains("\"title\": \"test_title\""));
+        assert!(!json.contains("\"validation_status\":"));
+
+        // a fetcher stands in for the remote server
+        let reader = crate::ManifestStoreReader::new();
+        reader
+            .set_fetcher(Box::new(TestFetcher(manifest_bytes)))
+            .expect("Failed to set fetcher");
+        let json = reader
+            .read_stream("image/jpeg", &output)
+            .expect("Failed to fetch remote manifest");
+        assert!(json.contains("\"title\": \"test_title\""));
+        assert!(!json.contains("\"validation_status\":"));
+    }
+
+    struct TestFetcher(Vec<u8>);
+
+    impl crate::ManifestFetcher for TestFetcher {
+        fn fetch(&self, url: String) -> crate::StreamResult<Vec<u8>> {
+            assert_eq!(url, REMOTE_URL);
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_manifest_builder_remote_embedded() {
+        let builder = remote_builder(false);
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = TestStream::new();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        builder
+            .sign_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+
+        let reader = crate::ManifestStoreReader::new();
+        let json = reader
+            .read_stream("image/jpeg", &output)
+            .expect("Failed to read embedded manifest");
+        assert!(json.contains("\"title\": \"test_title\""));
+        assert!(!json.contains("\"validation_status\":"));
+    }
+
+    #[test]
+    fn test_manifest_builder_remote_sidecar() {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: true,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        assert!(builder.set_remote_url(REMOTE_URL, false).is_err());
+    }
+
+    #[test]
+    fn test_manifest_builder_add_ingredient() {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        builder
+            .add_resource_stream("thumbnail", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to add thumbnail stream");
+
+        // an ingredient with a manifest store from a stream
+        let parent = TestStream::from_memory(INGREDIENT.to_vec());
+        builder
+            .add_ingredient_stream(
+                r#"{"title": "C.jpg", "relationship": "parentOf"}"#,
+                "auto",
+                &parent,
+            )
+            .expect("Failed to add ingredient stream");
+
+        // and one from an IngredientBuilder
+        let ingredient = crate::IngredientBuilder::new(&crate::IngredientBuilderSettings {
+            title: "A.jpg".to_string(),
+            format: "image/jpeg".to_string(),
+            instance_id: "xmp:iid:test".to_string(),
+            relationship: crate::Relationship::ComponentOf,
+        });
+        ingredient
+            .read_stream("image/jpeg", &TestStream::from_memory(IMAGE.to_vec()))
+            .expect("Failed to read ingredient");
+        builder
+            .add_ingredient(&ingredient)
+            .expect("Failed to add ingredient");
+
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let output = TestStream::new();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).expect("Signer config failed");
+        builder
+            .sign_stream(&signer, &input, &output)
+            .expect("Failed to sign");
+
+        let reader = crate::ManifestStoreReader::new();
+        let json = reader
+            .read_stream("image/jpeg", &output)
+            .expect("Failed to read signed asset");
+        let store: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let active = store["active_manifest"].as_str().unwrap();
+        let ingredients = store["manifests"][active]["ingredients"]
+            .as_array()
+            .unwrap();
+        assert_eq!(ingredients.len(), 2);
+        assert_eq!(ingredients[0]["title"], "C.jpg");
+        assert_eq!(ingredients[0]["relationship"], "parentOf");
+        assert!(ingredients[0]["active_manifest"].is_string());
+        assert!(ingredients[0]["thumbnail"].is_object());
+        assert_eq!(ingredients[1]["title"], "A.jpg");
+        assert!(ingredients[1]["thumbnail"].is_object());
+        // the manifest store of the parent is now part of the provenance chain
+        assert!(store["manifests"].as_object().unwrap().len() > 1);
+        assert!(!json.contains("\"validation_status\":"));
+    }
+
+    #[test]
+    fn test_manifest_builder_add_resource_stream() {
+        let settings = ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        };
+        let builder = ManifestBuilder::new(&settings);
+        builder
+            .from_json(MANIFEST_JSON)
+            .expect("Failed to load manifest Json");
+        let thumbnail = TestStream::from_memory(IMAGE.to_vec());
+        builder
+            .add_resource_stream("thumbnail", &thumbnail)
+            .expect("Failed to add thumbnail stream");
+        let manifest = builder.manifest.read().unwrap();
+        let resource = manifest.resources().get("thumbnail").unwrap();
+        assert_eq!(resource.as_slice(), IMAGE);
     }
 }
@@ -11,35 +11,34 @@
 // specific language governing permissions and limitations under
 // each license.
 
-use std::{io::Cursor, sync::RwLock};
+use std::{io::SeekFrom, sync::RwLock};
 
 use c2pa::ManifestStore;
 
-use crate::{C2paError, Result, Stream, StreamAdapter};
+use crate::{
+    format::resolve_format, C2paError, NativeStream, Result, Stream, StreamAdapter, StreamResult,
+};
 
-pub(crate) struct CAIReadWrapper<'a> {
-    pub reader: &'a mut dyn c2pa::CAIRead,
+/// Defines the callback interface for fetching remote manifest stores
+pub trait ManifestFetcher: Send + Sync {
+    /// Returns the bytes of the manifest store (.c2pa) at the url
+    fn fetch(&self, url: String) -> StreamResult<Vec<u8>>;
 }
 
-impl std::io::Read for CAIReadWrapper<'_> {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.reader.read(buf)
-    }
+/// Settings for a ManifestStoreReader
+#[derive(Clone, Default)]
+pub struct ManifestStoreReaderSettings {
+    /// The maximum size in bytes of a stream that will be read
+    /// Larger streams fail with a NotSupported error, None for no limit
+    pub max_stream_size: Option<u64>,
 }
 
-impl std::io::Seek for CAIReadWrapper<'_> {
-    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        self.reader.seek(pos)
-    }
-}
-
-struct ReaderSettings {}
-
 /// The ManifestStoreReader reads the manifest store from a stream and then
 /// provides access to the store via the json() and resource() methods.
 pub struct ManifestStoreReader {
-    _settings: ReaderSettings,
+    settings: ManifestStoreReaderSettings,
     store: RwLock<ManifestStore>,
+    fetcher: RwLock<Option<Box<dyn ManifestFetcher>>>,
 }
 
 impl ManifestStoreReader {
@@ -48,15 +47,40 @@ impl ManifestStoreReader {
     /// * `ManifestStoreReader` - the new ManifestStoreReader
     ///
     pub fn new() -> Self {
+        Self::with_settings(&ManifestStoreReaderSettings::default())
+    }
+
+    /// Creates a new ManifestStoreReader with the given settings
+    /// # Arguments
+    /// * `settings` - the settings for the reader
+    /// # Returns
+    /// * `ManifestStoreReader` - the new ManifestStoreReader
+    ///
+    pub fn with_settings(settings: &ManifestStoreReaderSettings) -> Self {
         Self {
-            _settings: ReaderSettings {},
+            settings: settings.clone(),
             store: RwLock::new(ManifestStore::new()),
+            fetcher: RwLock::new(None),
         }
     }
 
+    /// Sets the fetcher used to get manifest stores that are only referenced remotely
+    ///
+    /// Without a fetcher, reading an asset with only a remote reference
+    /// fails with a RemoteManifest error
+    /// # Arguments
+    /// * `fetcher` - the callback that returns the manifest store for a url
+    /// # Returns
+    /// * `Result<()>` - Ok(()) if successful, otherwise an error
+    ///
+    pub fn set_fetcher(&self, fetcher: Box<dyn ManifestFetcher>) -> Result<()> {
+        *self.fetcher.try_write().map_err(|_| C2paError::RwLock)? = Some(fetcher);
+        Ok(())
+    }
+
     /// Reads the manifest store from a stream
     /// # Arguments
-    /// * `format` - the format of the manifest store
+    /// * `format` - the format of the manifest store, empty or "auto" to detect it
     /// * `stream` - the stream to read from
     /// # Returns
     /// * `Result<String>` - the json representation of the manifest store
@@ -67,29 +91,112 @@ impl ManifestStoreReader {
         self.read(format, &mut stream)
     }
 
+    /// Reads the manifest store from a native stream, without calling back into the host
+    pub fn read_native_stream(&self, format: &str, stream: &NativeStream) -> Result<String> {
+        self.read_stream(format, stream)
+    }
+
     /// Reads the manifest store from a stream
+    ///
+    /// The stream is read in place, seeking only to the manifest store
+    /// and the hashed ranges, so the asset is never loaded into memory.
+    /// If the asset only references a remote manifest store, it is fetched
+    /// with the fetcher and validated against the asset loaded into memory
     /// # Arguments
-    /// * `format` - the format of the manifest store
+    /// * `format` - the format of the manifest store, empty or "auto" to detect it
     /// * `stream` - the stream to read from
     /// # Returns
     /// * `Result<String>` - the json representation of the manifest store
     ///    or an error
     ///
     pub fn read(&self, format: &str, stream: &mut dyn c2pa::CAIRead) -> Result<String> {
-        // todo: use ManifestStore::f
-        let mut bytes = Vec::new();
-        stream
-            .seek(std::io::SeekFrom::End(0))
+        let format = resolve_format(format, stream)?;
+        self.check_stream_size(stream)?;
+        stream.rewind().map_err(C2paError::from)?;
+        match ManifestStore::from_stream(&format, stream, true) {
+            Ok(store) => self.set_store(store),
+            Err(c2pa::Error::RemoteManifestUrl(url)) => {
+                let fetcher = self.fetcher.try_read().map_err(|_| C2paError::RwLock)?;
+                match fetcher.as_ref() {
+                    Some(fetcher) => {
+                        let manifest_bytes = fetcher
+                            .fetch(url)
+                            .map_err(|e| C2paError::RemoteManifest(e.to_string()))?;
+                        self.read_with_manifest_bytes(&format, &manifest_bytes, stream)
+                    }
+                    None => Err(C2paError::from(c2pa::Error::RemoteManifestUrl(url))),
+                }
+            }
+            Err(e) => Err(C2paError::from(e)),
+        }
+    }
+
+    /// Reads a sidecar manifest store and validates it against an asset stream
+    /// # Arguments
+    /// * `format` - the format of the asset, empty or "auto" to detect it
+    /// * `manifest_stream` - the stream to read the manifest store (.c2pa) from
+    /// * `asset_stream` - the stream to read the asset from
+    /// # Returns
+    /// * `Result<String>` - the json representation of the manifest store or an error
+    ///
+    pub fn read_with_manifest_stream(
+        &self,
+        format: &str,
+        manifest_stream: &dyn Stream,
+        asset_stream: &dyn Stream,
+    ) -> Result<String> {
+        let mut manifest_stream = StreamAdapter::from(manifest_stream);
+        let mut asset_stream = StreamAdapter::from(asset_stream);
+        self.read_with_manifest(format, &mut manifest_stream, &mut asset_stream)
+    }
+
+    /// Reads a sidecar manifest store and validates it against an asset
+    ///
+    /// The asset is loaded into memory to validate the hard bindings,
+    /// so the maximum stream size setting applies to it
+    /// # Arguments
+    /// * `format` - the format of the asset, empty or "auto" to detect it
+    /// * `manifest` - the stream to read the manifest store (.c2pa) from
+    /// * `asset` - the stream to read the asset from
+    /// # Returns
+    /// * `Result<String>` - the json representation of the manifest store or an error
+    ///
+    pub fn read_with_manifest(
+        &self,
+        format: &str,
+        manifest: &mut dyn c2pa::CAIRead,
+        asset: &mut dyn c2pa::CAIRead,
+    ) -> Result<String> {
+        let format = resolve_format(format, asset)?;
+        let mut manifest_bytes = Vec::new();
+        manifest.rewind().map_err(C2paError::from)?;
+        manifest
+            .read_to_end(&mut manifest_bytes)
             .map_err(C2paError::from)?;
-        stream
-            .seek(std::io::SeekFrom::Start(0))
+        self.read_with_manifest_bytes(&format, &manifest_bytes, asset)
+    }
+
+    // Validates a manifest store against an asset loaded into memory
+    fn read_with_manifest_bytes(
+        &self,
+        format: &str,
+        manifest_bytes: &[u8],
+        asset: &mut dyn c2pa::CAIRead,
+    ) -> Result<String> {
+        self.check_stream_size(asset)?;
+        let mut asset_bytes = Vec::new();
+        asset.rewind().map_err(C2paError::from)?;
+        asset
+            .read_to_end(&mut asset_bytes)
             .map_err(C2paError::from)?;
-        let _len = stream.read_to_end(&mut bytes).map_err(C2paError::from)?;
-        let bytes = CAIReadWrapper {
-            reader: &mut Cursor::new(bytes),
-        };
-        let stream = bytes.reader;
-        let store = ManifestStore::from_stream(format, stream, true).map_err(C2paError::from)?;
+        let store =
+            ManifestStore::from_manifest_and_asset_bytes(manifest_bytes, format, &asset_bytes)
+                .map_err(C2paError::from)?;
+        self.set_store(store)
+    }
+
+    // Replaces the current store, returning its json representation
+    fn set_store(&self, store: ManifestStore) -> Result<String> {
         let json = store.to_string();
         if let Ok(mut st) = self.store.try_write() {
             *st = store;
@@ -99,6 +206,19 @@ impl ManifestStoreReader {
         Ok(json)
     }
 
+    // Fails if the stream is larger than the configured maximum size
+    fn check_stream_size(&self, stream: &mut dyn c2pa::CAIRead) -> Result<()> {
+        if let Some(max_size) = self.settings.max_stream_size {
+            let len = stream.seek(SeekFrom::End(0)).map_err(C2paError::from)?;
+            if len > max_size {
+                return Err(C2paError::NotSupported(format!(
+                    "stream size {len} exceeds the maximum of {max_size} bytes"
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// returns a json representation of the manifest store
     /// # Returns
     /// * `Result<String>` - the json representation of the manifest store
@@ -158,8 +278,12 @@ impl ManifestStoreReader {
         id: &str,
         stream: &mut dyn c2pa::CAIReadWrite,
     ) -> Result<()> {
-        self.resource(manifest_label, id)
-            .and_then(|bytes| stream.write_all(&bytes).map_err(C2paError::from))
+        let bytes = self.resource(manifest_label, id)?;
+        stream.write_all(&bytes)?;
+        // a buffered stream must not leave the resource to be written when it is dropped,
+        // where an error would be lost
+        stream.flush()?;
+        Ok(())
     }
 }
 
@@ -187,4 +311,89 @@ mod tests {
         assert!(json.contains("\"title\": \"C.jpg\""));
         assert!(!json.contains("\"validation_status\":"));
     }
+
+    #[test]
+    fn test_manifest_store_reader_max_stream_size() {
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let settings = ManifestStoreReaderSettings {
+            max_stream_size: Some(IMAGE.len() as u64 - 1),
+        };
+        let reader = ManifestStoreReader::with_settings(&settings);
+        let result = reader.read_stream("image/jpeg", &input);
+        assert!(matches!(result, Err(C2paError::NotSupported(_))));
+
+        let settings = ManifestStoreReaderSettings {
+            max_stream_size: Some(IMAGE.len() as u64),
+        };
+        let reader = ManifestStoreReader::with_settings(&settings);
+        assert!(reader.read_stream("image/jpeg", &input).is_ok());
+    }
+
+    #[test]
+    fn test_manifest_store_reader_with_manifest() {
+        let manifest_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", IMAGE).unwrap();
+        let manifest = TestStream::from_memory(manifest_bytes);
+        let asset = TestStream::from_memory(IMAGE.to_vec());
+        let reader = ManifestStoreReader::new();
+        let json = reader
+            .read_with_manifest_stream("image/jpeg", &manifest, &asset)
+            .unwrap();
+        assert!(json.contains("\"title\": \"C.jpg\""));
+        assert!(!json.contains("\"validation_status\":"));
+
+        // the hard binding does not match a different asset
+        let other = TestStream::from_memory(include_bytes!("../tests/fixtures/A.jpg").to_vec());
+        let json = reader
+            .read_with_manifest_stream("auto", &manifest, &other)
+            .unwrap();
+        assert!(json.contains("\"validation_status\":"));
+    }
+
+    // A stream that fails every write, like a full disk
+    struct FullStream;
+
+    impl Stream for FullStream {
+        fn read_stream(&self, _length: u64) -> StreamResult<Vec<u8>> {
+            Ok(Vec::new())
+        }
+
+        fn seek_stream(&self, _pos: i64, _mode: crate::SeekMode) -> StreamResult<u64> {
+            Ok(0)
+        }
+
+        fn write_stream(&self, _data: Vec<u8>) -> StreamResult<u64> {
+            Err(crate::StreamError::Io {
+                reason: "disk full".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_manifest_store_reader_resource_write_error() {
+        let reader = ManifestStoreReader::new();
+        let json = reader
+            .read_stream("image/jpeg", &TestStream::from_memory(IMAGE.to_vec()))
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let label = value["active_manifest"].as_str().unwrap();
+        let id = value["manifests"][label]["thumbnail"]["identifier"]
+            .as_str()
+            .unwrap();
+
+        let output = TestStream::new();
+        reader.resource_write_stream(label, id, &output).unwrap();
+        assert_eq!(output.flush_count(), 1);
+        // the resource is smaller than the adapter buffer, so the write fails on flush
+        assert!(reader
+            .resource_write_stream(label, id, &FullStream)
+            .is_err());
+    }
+
+    #[test]
+    fn test_manifest_store_reader_auto_format() {
+        let input = TestStream::from_memory(IMAGE.to_vec());
+        let reader = ManifestStoreReader::new();
+        let json = reader.read_stream("auto", &input).unwrap();
+        assert!(json.contains("\"title\": \"C.jpg\""));
+    }
 }
@@ -0,0 +1,139 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use std::{
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    sync::RwLock,
+};
+
+use crate::{SeekMode, Stream, StreamError, StreamResult};
+
+/// A Stream over a byte buffer, implemented in Rust
+///
+/// A MemoryStream can be created from bytes or empty, and written to
+/// by signing, and its contents are then available from data()
+pub struct MemoryStream {
+    cursor: RwLock<Cursor<Vec<u8>>>,
+}
+
+impl MemoryStream {
+    /// Creates an empty memory stream
+    pub fn new() -> Self {
+        Self::from_bytes(Vec::new())
+    }
+
+    /// Creates a memory stream over bytes, positioned at the start
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            cursor: RwLock::new(Cursor::new(data)),
+        }
+    }
+
+    // Runs a cursor operation while holding the lock
+    fn with_cursor<T>(
+        &self,
+        f: impl FnOnce(&mut Cursor<Vec<u8>>) -> std::io::Result<T>,
+    ) -> StreamResult<T> {
+        let mut cursor = self.cursor.write().map_err(|_| StreamError::Other {
+            reason: "RwLock".to_string(),
+        })?;
+        f(&mut cursor).map_err(|e| StreamError::Io {
+            reason: e.to_string(),
+        })
+    }
+
+    /// Returns a copy of the contents of the stream
+    pub fn data(&self) -> StreamResult<Vec<u8>> {
+        self.with_cursor(|cursor| Ok(cursor.get_ref().clone()))
+    }
+
+    /// Returns the size of the contents of the stream
+    pub fn size(&self) -> StreamResult<u64> {
+        self.with_cursor(|cursor| Ok(cursor.get_ref().len() as u64))
+    }
+
+    /// Copies contents of the stream from an offset into a buffer,
+    /// returning the number of bytes copied
+    pub fn copy_data(&self, offset: usize, buf: &mut [u8]) -> StreamResult<usize> {
+        self.with_cursor(|cursor| {
+            let data = cursor.get_ref();
+            let start = offset.min(data.len());
+            let len = buf.len().min(data.len() - start);
+            buf[..len].copy_from_slice(&data[start..start + len]);
+            Ok(len)
+        })
+    }
+
+    /// Reads into a buffer, returning the number of bytes read
+    pub fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        self.with_cursor(|cursor| cursor.read(buf))
+    }
+
+    /// Writes a buffer, returning the number of bytes written
+    pub fn write_from(&self, buf: &[u8]) -> StreamResult<usize> {
+        self.with_cursor(|cursor| cursor.write(buf))
+    }
+}
+
+impl Default for MemoryStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stream for MemoryStream {
+    fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+        let mut data = vec![0u8; length as usize];
+        let len = self.read_into(&mut data)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+        let pos = match mode {
+            SeekMode::Start => SeekFrom::Start(pos as u64),
+            SeekMode::End => SeekFrom::End(pos),
+            SeekMode::Current => SeekFrom::Current(pos),
+        };
+        self.with_cursor(|cursor| cursor.seek(pos))
+    }
+
+    fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        self.write_from(&data).map(|len| len as u64)
+    }
+
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        self.size().map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_stream() {
+        let stream = MemoryStream::from_bytes(vec![1, 2, 3]);
+        assert_eq!(stream.read_stream(2).unwrap(), vec![1, 2]);
+        assert_eq!(stream.seek_stream(0, SeekMode::End).unwrap(), 3);
+        assert_eq!(stream.write_stream(vec![4, 5]).unwrap(), 2);
+        assert_eq!(stream.data().unwrap(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(stream.size().unwrap(), 5);
+        assert_eq!(stream.stream_length().unwrap(), Some(5));
+
+        let mut buf = [0u8; 4];
+        assert_eq!(stream.copy_data(3, &mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [4, 5]);
+        assert_eq!(stream.copy_data(10, &mut buf).unwrap(), 0);
+    }
+}
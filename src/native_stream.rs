@@ -0,0 +1,126 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use crate::{FileStream, MemoryStream, SeekMode, SpoolStream, Stream, StreamError, StreamResult};
+
+/// A Stream implemented in Rust, passed as an object instead of a Stream callback
+///
+/// Foreign languages cannot pass Rust objects where a Stream callback is expected,
+/// so every native stream is created as a NativeStream, and the read and sign
+/// methods that take one never call back into the host language for its data
+pub enum NativeStream {
+    File(FileStream),
+    Memory(MemoryStream),
+    Spool(SpoolStream),
+}
+
+impl NativeStream {
+    /// Opens a stream over a file
+    /// # Arguments
+    /// * `path` - the path of the file
+    /// * `mode` - an fopen style mode: "r", "w", "a", optionally with "+" and "b"
+    ///
+    pub fn open_file(path: &str, mode: &str) -> StreamResult<Self> {
+        FileStream::new(path, mode).map(Self::File)
+    }
+
+    /// Creates an empty memory stream, to be written to by signing
+    pub fn memory() -> Self {
+        Self::Memory(MemoryStream::new())
+    }
+
+    /// Creates a memory stream over bytes
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self::Memory(MemoryStream::from_bytes(data))
+    }
+
+    /// Creates a seekable stream over a forward-only source
+    ///
+    /// The spool stream takes ownership of the source, which is dropped along with it.
+    /// From a foreign language the source must not be used after this call
+    pub fn spool(source: Box<dyn Stream>, threshold: u64) -> StreamResult<Self> {
+        SpoolStream::new(source, threshold).map(Self::Spool)
+    }
+
+    fn stream(&self) -> &dyn Stream {
+        match self {
+            Self::File(stream) => stream,
+            Self::Memory(stream) => stream,
+            Self::Spool(stream) => stream,
+        }
+    }
+
+    /// Returns a copy of the contents of a memory stream
+    pub fn data(&self) -> StreamResult<Vec<u8>> {
+        match self {
+            Self::Memory(stream) => stream.data(),
+            _ => Err(StreamError::Other {
+                reason: "only memory streams have data".to_string(),
+            }),
+        }
+    }
+
+    /// Returns true if a spool stream has moved its data to a temporary file
+    pub fn is_spooled_to_file(&self) -> StreamResult<bool> {
+        match self {
+            Self::Spool(stream) => stream.is_spooled_to_file(),
+            _ => Ok(false),
+        }
+    }
+}
+
+impl Stream for NativeStream {
+    fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+        self.stream().read_stream(length)
+    }
+
+    fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+        self.stream().seek_stream(pos, mode)
+    }
+
+    fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        self.stream().write_stream(data)
+    }
+
+    fn flush_stream(&self) -> StreamResult<()> {
+        self.stream().flush_stream()
+    }
+
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        self.stream().stream_length()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_stream::TestStream;
+
+    #[test]
+    fn test_native_stream() {
+        let stream = NativeStream::from_bytes(vec![1, 2, 3]);
+        assert_eq!(stream.read_stream(2).unwrap(), vec![1, 2]);
+        assert_eq!(stream.stream_length().unwrap(), Some(3));
+        assert_eq!(stream.data().unwrap(), vec![1, 2, 3]);
+        assert!(!stream.is_spooled_to_file().unwrap());
+
+        let stream = NativeStream::open_file("tests/fixtures/C.jpg", "rb").unwrap();
+        assert!(stream.data().is_err());
+        assert!(NativeStream::open_file("tests/fixtures/C.jpg", "x").is_err());
+
+        let source = TestStream::from_memory(vec![0; 16]);
+        let stream = NativeStream::spool(Box::new(source), 0).unwrap();
+        assert_eq!(stream.read_stream(4).unwrap().len(), 4);
+        assert!(stream.is_spooled_to_file().unwrap());
+    }
+}
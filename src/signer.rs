@@ -12,7 +12,7 @@
 // each license.
 
 use std::str::FromStr;
-use std::sync::RwLock;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
 
 use crate::stream::StreamResult;
 use crate::{C2paError, Result};
@@ -74,6 +74,36 @@ impl C2paSigner {
         }
     }
 
+    /// Creates a signer that signs with a private key, without a callback
+    /// # Arguments
+    /// * `alg` - the signing algorithm, one of es256, es384, es512, ps256, ps384, ps512 or ed25519
+    /// * `certs` - the PEM encoded certificate chain, starting with the signing certificate
+    /// * `private_key` - the PEM encoded private key
+    /// * `tsa_url` - an optional url of a time stamp authority
+    /// # Returns
+    /// * `Result<C2paSigner>` - the signer, or an error if the keys do not match the algorithm
+    ///
+    pub fn from_keys(
+        alg: &str,
+        certs: &str,
+        private_key: &str,
+        tsa_url: Option<String>,
+    ) -> Result<Self> {
+        let config = SignerConfig {
+            alg: alg.to_lowercase(),
+            certs: certs.as_bytes().to_vec(),
+            time_authority_url: tsa_url,
+            use_ocsp: false,
+        };
+        let signer = Self::new(Box::new(KeySigner::new(
+            &config.alg,
+            &config.certs,
+            private_key.as_bytes(),
+        )?));
+        signer.configure(&config)?;
+        Ok(signer)
+    }
+
     /// Configure the signer with the given config
     /// # Arguments
     /// * `config` - the configuration for the signer
@@ -96,36 +126,69 @@ impl C2paSigner {
         }
         Ok(())
     }
+
+    // A poisoned lock still holds usable settings, so it is not treated as an error
+    fn settings(&self) -> RwLockReadGuard<'_, SignerInternalConfig> {
+        self.settings.read().unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
 impl c2pa::Signer for C2paSigner {
     fn sign(&self, data: &[u8]) -> c2pa::Result<Vec<u8>> {
+        // the error is boxed so it can be reported as a signature error
         self.callback
             .sign(data.to_vec())
-            .map_err(|e| c2pa::Error::BadParam(e.to_string()))
+            .map_err(|e| c2pa::Error::OtherError(Box::new(e)))
     }
 
     fn alg(&self) -> c2pa::SigningAlg {
-        RwLock::read(&self.settings).unwrap().alg
+        self.settings().alg
     }
 
     fn certs(&self) -> c2pa::Result<Vec<Vec<u8>>> {
-        Ok(RwLock::read(&self.settings).unwrap().certs.clone())
+        Ok(self.settings().certs.clone())
     }
 
     fn reserve_size(&self) -> usize {
-        RwLock::read(&self.settings).unwrap().reserve_size as usize
+        self.settings().reserve_size as usize
     }
 
     fn time_authority_url(&self) -> Option<String> {
-        RwLock::read(&self.settings)
-            .unwrap()
-            .time_authority_url
-            .clone()
+        self.settings().time_authority_url.clone()
     }
 
     fn ocsp_val(&self) -> Option<Vec<u8>> {
-        RwLock::read(&self.settings).unwrap().ocsp_val.clone()
+        self.settings().ocsp_val.clone()
+    }
+}
+
+/// A SignerCallback that signs with a private key using the sdk signers
+///
+/// The sdk signers are not Send or Sync, so one is created for each signature
+struct KeySigner {
+    alg: c2pa::SigningAlg,
+    certs: Vec<u8>,
+    private_key: Vec<u8>,
+}
+
+impl KeySigner {
+    fn new(alg: &str, certs: &[u8], private_key: &[u8]) -> Result<Self> {
+        let alg = c2pa::SigningAlg::from_str(alg).map_err(|e| C2paError::Other(e.to_string()))?;
+        // create a signer to validate the keys up front
+        c2pa::create_signer::from_keys(certs, private_key, alg, None)?;
+        Ok(Self {
+            alg,
+            certs: certs.to_vec(),
+            private_key: private_key.to_vec(),
+        })
+    }
+}
+
+impl SignerCallback for KeySigner {
+    fn sign(&self, data: Vec<u8>) -> StreamResult<Vec<u8>> {
+        let signer = c2pa::create_signer::from_keys(&self.certs, &self.private_key, self.alg, None)
+            .map_err(C2paError::from)?;
+        Ok(signer.sign(&data).map_err(C2paError::from)?)
     }
 }
 
@@ -155,4 +218,51 @@ mod tests {
         println!("signature len = {}", signature.len());
         assert!(signature.len() <= signer.reserve_size());
     }
+
+    #[test]
+    fn test_from_keys() {
+        let certs = include_str!("../tests/fixtures/es256_certs.pem");
+        let private_key = include_str!("../tests/fixtures/es256_private.key");
+        let signer = C2paSigner::from_keys("ES256", certs, private_key, None).unwrap();
+        assert_eq!(signer.alg(), c2pa::SigningAlg::Es256);
+        let signature = signer.sign(b"some sample content to sign").unwrap();
+        assert!(!signature.is_empty());
+        assert!(signature.len() <= signer.reserve_size());
+
+        // the key must match the algorithm
+        assert!(C2paSigner::from_keys("ps256", certs, private_key, None).is_err());
+        assert!(C2paSigner::from_keys("es999", certs, private_key, None).is_err());
+    }
+
+    #[test]
+    fn test_shared_signer() {
+        let certs = include_str!("../tests/fixtures/es256_certs.pem");
+        let private_key = include_str!("../tests/fixtures/es256_private.key");
+        let signer =
+            std::sync::Arc::new(C2paSigner::from_keys("es256", certs, private_key, None).unwrap());
+
+        // one signer is used by several builders on different threads
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let signer = signer.clone();
+                std::thread::spawn(move || {
+                    let builder = crate::ManifestBuilder::new(&crate::ManifestBuilderSettings {
+                        generator: "test".to_string(),
+                        sidecar: false,
+                    });
+                    builder
+                        .from_json(r#"{"title": "test", "format": "image/jpeg"}"#)
+                        .unwrap();
+                    let image = include_bytes!("../tests/fixtures/A.jpg").to_vec();
+                    let mut input = std::io::Cursor::new(image);
+                    let mut output = std::io::Cursor::new(Vec::new());
+                    builder.sign(&*signer, &mut input, &mut output).unwrap();
+                    output.into_inner().len()
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert!(thread.join().unwrap() > 0);
+        }
+    }
 }
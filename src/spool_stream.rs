@@ -0,0 +1,305 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    sync::RwLock,
+};
+
+use crate::{SeekMode, Stream, StreamError, StreamResult};
+
+// The size of each read from the source
+const CHUNK_SIZE: u64 = 0x10000;
+
+/// A seekable Stream over a forward-only source
+///
+/// The source is only ever read from start to end, as far as needed.
+/// What has been read is spooled in memory up to the threshold,
+/// and then moved to a temporary file that is deleted with the stream
+pub struct SpoolStream {
+    source: Box<dyn Stream>,
+    threshold: u64,
+    spool: RwLock<Spool>,
+}
+
+// The spooled part of the source
+#[derive(Default)]
+struct Spool {
+    data: Vec<u8>,
+    file: Option<File>,
+    len: u64,
+    pos: u64,
+    exhausted: bool,
+}
+
+impl SpoolStream {
+    /// Creates a spool stream
+    ///
+    /// The spool stream takes ownership of the source, which is dropped along with it,
+    /// even if creating the spool stream fails
+    /// # Arguments
+    /// * `source` - the forward-only stream to read from, only read_stream is called
+    /// * `threshold` - the number of bytes to keep in memory before spooling to a file
+    /// # Returns
+    /// * `StreamResult<SpoolStream>` - the stream, or an error if the threshold cannot be held in memory
+    ///
+    pub fn new(source: Box<dyn Stream>, threshold: u64) -> StreamResult<Self> {
+        if threshold > isize::MAX as u64 {
+            return Err(StreamError::Other {
+                reason: format!("spool threshold {threshold} is too large"),
+            });
+        }
+        Ok(Self {
+            source,
+            threshold,
+            spool: RwLock::new(Spool::default()),
+        })
+    }
+
+    // Runs an operation on the spool while holding the lock
+    fn with_spool<T>(&self, f: impl FnOnce(&mut Spool) -> StreamResult<T>) -> StreamResult<T> {
+        let mut spool = self.spool.write().map_err(|_| StreamError::Other {
+            reason: "RwLock".to_string(),
+        })?;
+        f(&mut spool)
+    }
+
+    // Reads from the source until len bytes are spooled, or to the end if len is None
+    #[allow(clippy::unnecessary_map_or)]
+    fn fill(&self, spool: &mut Spool, len: Option<u64>) -> StreamResult<()> {
+        while !spool.exhausted && len.map_or(true, |len| spool.len < len) {
+            let chunk = self.source.read_stream(CHUNK_SIZE)?;
+            if chunk.is_empty() {
+                spool.exhausted = true;
+            } else {
+                self.append(spool, &chunk).map_err(io_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&self, spool: &mut Spool, chunk: &[u8]) -> std::io::Result<()> {
+        if spool.file.is_none() && spool.len + chunk.len() as u64 > self.threshold {
+            let mut file = tempfile::tempfile()?;
+            file.write_all(&spool.data)?;
+            spool.data = Vec::new();
+            spool.file = Some(file);
+        }
+        match spool.file.as_mut() {
+            Some(file) => {
+                file.seek(SeekFrom::End(0))?;
+                file.write_all(chunk)?;
+            }
+            None => spool.data.extend_from_slice(chunk),
+        }
+        spool.len += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Reads into a buffer, returning the number of bytes read
+    pub fn read_into(&self, buf: &mut [u8]) -> StreamResult<usize> {
+        self.with_spool(|spool| {
+            self.fill(spool, Some(spool.pos + buf.len() as u64))?;
+            let len = spool.len.saturating_sub(spool.pos).min(buf.len() as u64) as usize;
+            let buf = &mut buf[..len];
+            match spool.file.as_mut() {
+                Some(file) => {
+                    file.seek(SeekFrom::Start(spool.pos)).map_err(io_error)?;
+                    file.read_exact(buf).map_err(io_error)?;
+                }
+                None => {
+                    let start = spool.pos as usize;
+                    buf.copy_from_slice(&spool.data[start..start + len]);
+                }
+            }
+            spool.pos += len as u64;
+            Ok(len)
+        })
+    }
+
+    /// A spool stream is read only, so writing always fails
+    pub fn write_from(&self, _buf: &[u8]) -> StreamResult<usize> {
+        Err(StreamError::Other {
+            reason: "cannot write to a spool stream".to_string(),
+        })
+    }
+
+    /// Returns true if the spooled data has been moved to a temporary file
+    pub fn is_spooled_to_file(&self) -> StreamResult<bool> {
+        self.with_spool(|spool| Ok(spool.file.is_some()))
+    }
+}
+
+fn io_error(e: std::io::Error) -> StreamError {
+    StreamError::Io {
+        reason: e.to_string(),
+    }
+}
+
+impl Stream for SpoolStream {
+    fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+        let mut data = vec![0u8; length as usize];
+        let len = self.read_into(&mut data)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+        self.with_spool(|spool| {
+            let base = match mode {
+                SeekMode::Start => 0,
+                SeekMode::Current => spool.pos,
+                SeekMode::End => {
+                    self.fill(spool, None)?;
+                    spool.len
+                }
+            };
+            spool.pos = base
+                .checked_add_signed(pos)
+                .ok_or_else(|| StreamError::Other {
+                    reason: "seek to a negative position".to_string(),
+                })?;
+            Ok(spool.pos)
+        })
+    }
+
+    fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        self.write_from(&data).map(|len| len as u64)
+    }
+
+    // The length is only known once the source has been read to the end
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        self.with_spool(|spool| Ok(spool.exhausted.then_some(spool.len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_signer::TestSigner, test_stream::TestStream, C2paSigner, IngredientBuilder,
+        IngredientBuilderSettings, ManifestBuilder, ManifestBuilderSettings, ManifestStoreReader,
+        NativeStream, Relationship,
+    };
+
+    // A stream that can only be read from start to end, like an upload
+    struct ForwardOnlyStream(TestStream);
+
+    impl Stream for ForwardOnlyStream {
+        fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+            self.0.read_stream(length)
+        }
+
+        fn seek_stream(&self, _pos: i64, _mode: SeekMode) -> StreamResult<u64> {
+            Err(StreamError::Other {
+                reason: "seek in a forward-only stream".to_string(),
+            })
+        }
+
+        fn write_stream(&self, _data: Vec<u8>) -> StreamResult<u64> {
+            Err(StreamError::Other {
+                reason: "write to a forward-only stream".to_string(),
+            })
+        }
+    }
+
+    fn spool(path: &str, threshold: u64) -> NativeStream {
+        let data = std::fs::read(path).unwrap();
+        NativeStream::spool(
+            Box::new(ForwardOnlyStream(TestStream::from_memory(data))),
+            threshold,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_spool_stream() {
+        let source = TestStream::from_memory((0..=255).collect());
+        let stream = SpoolStream::new(Box::new(source), 1024).unwrap();
+        assert!(SpoolStream::new(Box::new(TestStream::new()), u64::MAX).is_err());
+        assert_eq!(stream.read_stream(4).unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(stream.stream_length().unwrap(), None);
+        assert_eq!(stream.seek_stream(100, SeekMode::Start).unwrap(), 100);
+        assert_eq!(stream.read_stream(2).unwrap(), vec![100, 101]);
+        assert_eq!(stream.seek_stream(-2, SeekMode::End).unwrap(), 254);
+        assert_eq!(stream.stream_length().unwrap(), Some(256));
+        assert_eq!(stream.read_stream(10).unwrap(), vec![254, 255]);
+        assert_eq!(stream.seek_stream(-255, SeekMode::Current).unwrap(), 1);
+        assert_eq!(stream.read_stream(1).unwrap(), vec![1]);
+        assert!(stream.seek_stream(-3, SeekMode::Current).is_err());
+        assert!(stream.write_stream(vec![0]).is_err());
+        assert!(!stream.is_spooled_to_file().unwrap());
+    }
+
+    #[test]
+    fn test_spool_stream_to_file() {
+        let data: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| i as u8).collect();
+        let stream =
+            SpoolStream::new(Box::new(TestStream::from_memory(data.clone())), 100).unwrap();
+        assert_eq!(stream.read_stream(10).unwrap(), data[..10]);
+        assert!(stream.is_spooled_to_file().unwrap());
+        assert_eq!(
+            stream.seek_stream(0, SeekMode::End).unwrap(),
+            data.len() as u64
+        );
+        assert_eq!(stream.seek_stream(5, SeekMode::Start).unwrap(), 5);
+        assert_eq!(stream.read_stream(CHUNK_SIZE * 4).unwrap(), data[5..]);
+    }
+
+    #[test]
+    fn test_spool_stream_forward_only() {
+        let data = std::fs::read("tests/fixtures/C.jpg").unwrap();
+        let source = ForwardOnlyStream(TestStream::from_memory(data));
+        assert!(ManifestStoreReader::new()
+            .read_stream("image/jpeg", &source)
+            .is_err());
+        let json = ManifestStoreReader::new()
+            .read_native_stream("image/jpeg", &spool("tests/fixtures/C.jpg", 1024))
+            .unwrap();
+        assert!(json.contains("C.jpg"));
+
+        let ingredient = IngredientBuilder::new(&IngredientBuilderSettings {
+            title: "C.jpg".to_string(),
+            format: "image/jpeg".to_string(),
+            instance_id: "xmp:iid:test".to_string(),
+            relationship: Relationship::ParentOf,
+        });
+        let source = spool("tests/fixtures/C.jpg", 0);
+        ingredient
+            .read_native_stream("image/jpeg", &source)
+            .unwrap();
+        assert!(source.is_spooled_to_file().unwrap());
+
+        let builder = ManifestBuilder::new(&ManifestBuilderSettings {
+            generator: "test".to_string(),
+            sidecar: false,
+        });
+        builder
+            .from_json(r#"{"title": "spooled", "format": "image/jpeg"}"#)
+            .unwrap();
+        let test_signer = Box::new(TestSigner::new());
+        let config = test_signer.config();
+        let signer = C2paSigner::new(test_signer);
+        signer.configure(&config).unwrap();
+        let output = NativeStream::memory();
+        builder
+            .sign_native_stream(&signer, &spool("tests/fixtures/A.jpg", 1024), &output)
+            .unwrap();
+        output.seek_stream(0, SeekMode::Start).unwrap();
+        let json = ManifestStoreReader::new()
+            .read_native_stream("image/jpeg", &output)
+            .unwrap();
+        assert!(json.contains("spooled"));
+    }
+}
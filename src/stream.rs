@@ -65,6 +65,16 @@ pub trait Stream: Send + Sync {
     fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64>;
     /// Write a stream of bytes to the stream
     fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64>;
+    /// Flush any buffered writes, called when signing has written the output
+    fn flush_stream(&self) -> StreamResult<()> {
+        Ok(())
+    }
+    /// Return the length of the stream if it can be found cheaply, or None
+    /// to have the length found by seeking to the end instead.
+    /// An error is treated like None, so host streams need not implement it
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        Ok(None)
+    }
 }
 
 impl Stream for Box<dyn Stream> {
@@ -79,6 +89,14 @@ impl Stream for Box<dyn Stream> {
     fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
         (**self).write_stream(data)
     }
+
+    fn flush_stream(&self) -> StreamResult<()> {
+        (**self).flush_stream()
+    }
+
+    fn stream_length(&self) -> StreamResult<Option<u64>> {
+        (**self).stream_length()
+    }
 }
 
 impl AsMut<dyn Stream> for dyn Stream {
@@ -87,21 +105,130 @@ impl AsMut<dyn Stream> for dyn Stream {
     }
 }
 
+/// Adapts a Stream to the Rust Read, Write and Seek traits
+///
+/// Calls to the Stream are buffered, since each one may cross into a host language.
+/// Reads fill a read-ahead window of buffer_size bytes, and writes are coalesced
+/// until buffer_size bytes are pending, a seek or a flush.
+/// A buffer size of 0 passes every call straight to the Stream.
+///
+/// The Stream methods take &self, so an adapter only needs a shared reference.
+/// A buffered adapter keeps its own read-ahead window and position, so only one
+/// buffered adapter may use a Stream at a time: dropping it returns the Stream
+/// to the logical position with all writes passed on, ready for the next adapter.
+/// Unbuffered adapters keep no state and may be used together on the same Stream.
+/// The Stream is responsible for synchronizing calls from several threads.
 pub struct StreamAdapter<'a> {
-    pub stream: &'a mut dyn Stream,
+    pub stream: &'a dyn Stream,
+    buffer_size: usize,
+    // bytes read ahead from the stream, consumed from read_pos
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    // bytes written that have not been passed to the stream yet
+    write_buf: Vec<u8>,
+    // the logical position, once it is known from a seek
+    pos: Option<u64>,
 }
 
 impl<'a> StreamAdapter<'a> {
+    /// The default size of the read-ahead window and of coalesced writes
+    pub const DEFAULT_BUFFER_SIZE: usize = 0x10000;
+
     pub fn from_stream_mut(stream: &'a mut dyn Stream) -> Self {
-        Self { stream }
+        Self::with_buffer_size(stream, Self::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates an adapter with a buffer of buffer_size bytes, 0 for no buffering
+    pub fn with_buffer_size(stream: &'a dyn Stream, buffer_size: usize) -> Self {
+        Self {
+            stream,
+            buffer_size,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write_buf: Vec::new(),
+            pos: None,
+        }
+    }
+
+    // The number of bytes read ahead of the logical position
+    fn read_ahead(&self) -> usize {
+        self.read_buf.len() - self.read_pos
+    }
+
+    // Discards the read-ahead window, returning the stream to the logical position
+    fn discard_read_ahead(&mut self) -> std::io::Result<()> {
+        let ahead = self.read_ahead();
+        self.read_buf.clear();
+        self.read_pos = 0;
+        if ahead > 0 {
+            self.stream
+                .seek_stream(-(ahead as i64), SeekMode::Current)
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    // Passes all of a buffer to the stream
+    fn write_all_to_stream(&mut self, mut buf: &[u8]) -> std::io::Result<()> {
+        while !buf.is_empty() {
+            let len = self
+                .stream
+                .write_stream(buf.to_vec())
+                .map_err(std::io::Error::other)? as usize;
+            if len == 0 {
+                return Err(std::io::ErrorKind::WriteZero.into());
+            }
+            buf = &buf[len.min(buf.len())..];
+        }
+        Ok(())
+    }
+
+    // Passes any coalesced writes to the stream
+    fn flush_writes(&mut self) -> std::io::Result<()> {
+        if !self.write_buf.is_empty() {
+            let buf = std::mem::take(&mut self.write_buf);
+            self.write_all_to_stream(&buf)?;
+        }
+        Ok(())
+    }
+
+    // Moves within the read-ahead window if the target is inside it
+    fn seek_in_window(&mut self, pos: SeekFrom) -> Option<u64> {
+        let current = self.pos?;
+        let offset = match pos {
+            SeekFrom::Start(target) => target as i64 - current as i64,
+            SeekFrom::Current(offset) => offset,
+            SeekFrom::End(_) => return None,
+        };
+        let read_pos = self.read_pos as i64 + offset;
+        if self.read_buf.is_empty() || read_pos < 0 || read_pos > self.read_buf.len() as i64 {
+            return None;
+        }
+        self.read_pos = read_pos as usize;
+        let pos = (current as i64 + offset) as u64;
+        self.pos = Some(pos);
+        Some(pos)
+    }
+
+    fn advance(&mut self, len: usize) {
+        if let Some(pos) = self.pos.as_mut() {
+            *pos += len as u64;
+        }
     }
 }
 
 impl<'a> From<&'a dyn Stream> for StreamAdapter<'a> {
     fn from(stream: &'a dyn Stream) -> Self {
-        let stream = &*stream as *const dyn Stream as *mut dyn Stream;
-        let stream = unsafe { &mut *stream };
-        Self { stream }
+        Self::with_buffer_size(stream, Self::DEFAULT_BUFFER_SIZE)
+    }
+}
+
+impl<'a> Drop for StreamAdapter<'a> {
+    // Leaves the stream at the logical position with all writes passed on,
+    // so it can be used again after the adapter
+    fn drop(&mut self) {
+        let _ = self.flush_writes();
+        let _ = self.discard_read_ahead();
     }
 }
 
@@ -111,44 +238,92 @@ impl<'a> c2pa::CAIReadWrite for StreamAdapter<'a> {}
 
 impl<'a> Read for StreamAdapter<'a> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut bytes = self
-            .stream
-            .read_stream(buf.len() as u64)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        let len = bytes.len();
-        buf.iter_mut().zip(bytes.drain(..)).for_each(|(dest, src)| {
-            *dest = src;
-        });
-        //println!("read: {:?}", len);
+        self.flush_writes()?;
+        if self.read_ahead() == 0 {
+            self.read_buf.clear();
+            self.read_pos = 0;
+            if buf.len() >= self.buffer_size {
+                // large reads gain nothing from the window
+                let bytes = self
+                    .stream
+                    .read_stream(buf.len() as u64)
+                    .map_err(std::io::Error::other)?;
+                let len = bytes.len().min(buf.len());
+                buf[..len].copy_from_slice(&bytes[..len]);
+                self.advance(len);
+                return Ok(len);
+            }
+            self.read_buf = self
+                .stream
+                .read_stream(self.buffer_size as u64)
+                .map_err(std::io::Error::other)?;
+        }
+        let len = self.read_ahead().min(buf.len());
+        buf[..len].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + len]);
+        self.read_pos += len;
+        self.advance(len);
         Ok(len)
     }
 }
 
 impl<'a> Seek for StreamAdapter<'a> {
     fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.flush_writes()?;
+        // a known length turns a seek from the end into one from the start,
+        // which may not need to seek the stream at all.
+        // The length is only a hint, so a stream that fails to report it,
+        // such as a host stream written before stream_length existed, is seeked instead
+        let pos = match pos {
+            SeekFrom::End(offset) => match self.stream.stream_length().ok().flatten() {
+                Some(len) => SeekFrom::Start(len.checked_add_signed(offset).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "seek to a negative position",
+                    )
+                })?),
+                None => pos,
+            },
+            _ => pos,
+        };
+        if let Some(pos) = self.seek_in_window(pos) {
+            return Ok(pos);
+        }
+        // the stream is ahead of the logical position by the read-ahead window
+        let ahead = self.read_ahead() as i64;
+        self.read_buf.clear();
+        self.read_pos = 0;
         let (pos, mode) = match pos {
-            SeekFrom::Current(pos) => (pos, SeekMode::Current),
+            SeekFrom::Current(pos) => (pos - ahead, SeekMode::Current),
             SeekFrom::Start(pos) => (pos as i64, SeekMode::Start),
             SeekFrom::End(pos) => (pos, SeekMode::End),
         };
-        //println!("Stream Seek {}", pos);
-        self.stream
+        let pos = self
+            .stream
             .seek_stream(pos, mode)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+            .map_err(std::io::Error::other)?;
+        self.pos = Some(pos);
+        Ok(pos)
     }
 }
 
 impl<'a> Write for StreamAdapter<'a> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let len = self
-            .stream
-            .write_stream(buf.to_vec())
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        Ok(len as usize)
+        self.discard_read_ahead()?;
+        if self.write_buf.len() + buf.len() > self.buffer_size {
+            self.flush_writes()?;
+        }
+        if buf.len() >= self.buffer_size {
+            self.write_all_to_stream(buf)?;
+        } else {
+            self.write_buf.extend_from_slice(buf);
+        }
+        self.advance(buf.len());
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        self.flush_writes()?;
+        self.stream.flush_stream().map_err(std::io::Error::other)
     }
 }
 
@@ -171,7 +346,7 @@ mod tests {
     #[test]
     fn test_stream_seek() {
         let mut test = TestStream::from_memory(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
-        let mut stream = StreamAdapter { stream: &mut test };
+        let mut stream = StreamAdapter::from_stream_mut(&mut test);
         let pos = stream.seek(SeekFrom::Start(5)).unwrap();
         assert_eq!(pos, 5);
         let mut buf = [0u8; 5];
@@ -183,7 +358,7 @@ mod tests {
     #[test]
     fn test_stream_write() {
         let mut test = TestStream::new();
-        let mut stream = StreamAdapter { stream: &mut test };
+        let mut stream = StreamAdapter::from_stream_mut(&mut test);
         let len = stream.write(&[0, 1, 2, 3, 4]).unwrap();
         assert_eq!(len, 5);
         stream.seek(SeekFrom::Start(0)).unwrap();
@@ -192,4 +367,253 @@ mod tests {
         assert_eq!(len, 5);
         assert_eq!(buf, [0, 1, 2, 3, 4]);
     }
+
+    #[test]
+    fn test_stream_read_ahead() {
+        let test = TestStream::from_memory((0..=255).collect());
+        let mut stream = StreamAdapter::with_buffer_size(&test, 64);
+        let mut byte = [0u8; 1];
+        for i in 0..100 {
+            stream.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], i);
+        }
+        assert_eq!(stream.stream_position().unwrap(), 100);
+        drop(stream);
+        // two windows of 64 bytes and the seek, which returns the stream to the logical position
+        assert_eq!(test.call_count(), 3);
+        // the stream is left at the logical position
+        assert_eq!(test.read_stream(1).unwrap(), vec![100]);
+    }
+
+    #[test]
+    fn test_stream_seek_in_window() {
+        let test = TestStream::from_memory((0..=255).collect());
+        let mut stream = StreamAdapter::with_buffer_size(&test, 64);
+        assert_eq!(stream.seek(SeekFrom::Start(10)).unwrap(), 10);
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [10, 11, 12, 13]);
+        assert_eq!(stream.seek(SeekFrom::Current(-2)).unwrap(), 12);
+        assert_eq!(stream.seek(SeekFrom::Start(40)).unwrap(), 40);
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [40, 41, 42, 43]);
+        assert_eq!(stream.seek(SeekFrom::Current(100)).unwrap(), 144);
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [144, 145, 146, 147]);
+        assert_eq!(stream.seek(SeekFrom::End(-1)).unwrap(), 255);
+        stream.read_exact(&mut buf[..1]).unwrap();
+        assert_eq!(buf[0], 255);
+        drop(stream);
+        // the seeks within the window are not passed to the stream
+        assert_eq!(test.call_count(), 6);
+    }
+
+    #[test]
+    fn test_stream_write_coalescing() {
+        let test = TestStream::new();
+        let mut stream = StreamAdapter::with_buffer_size(&test, 64);
+        for i in 0..100u8 {
+            assert_eq!(stream.write(&[i]).unwrap(), 1);
+        }
+        stream.flush().unwrap();
+        drop(stream);
+        // one write for each full buffer, and one for the rest on flush
+        assert_eq!(test.call_count(), 2);
+        test.seek_stream(0, SeekMode::Start).unwrap();
+        assert_eq!(
+            test.read_stream(200).unwrap(),
+            (0..100).collect::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn test_stream_read_write() {
+        let test = TestStream::from_memory(vec![0; 10]);
+        let mut stream = StreamAdapter::with_buffer_size(&test, 64);
+        let mut buf = [0u8; 2];
+        stream.read_exact(&mut buf).unwrap();
+        // a write after a read lands at the logical position, not after the window
+        stream.write_all(&[1, 2]).unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        stream.write_all(&[3]).unwrap();
+        assert_eq!(stream.stream_position().unwrap(), 7);
+        stream.seek(SeekFrom::Start(0)).unwrap();
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).unwrap();
+        assert_eq!(data, [0, 0, 1, 2, 0, 0, 3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_stream_unbuffered() {
+        let test = TestStream::from_memory((0..=255).collect());
+        let mut stream = StreamAdapter::with_buffer_size(&test, 0);
+        let mut byte = [0u8; 1];
+        for _ in 0..100 {
+            stream.read_exact(&mut byte).unwrap();
+        }
+        stream.write_all(&[0]).unwrap();
+        drop(stream);
+        assert_eq!(test.call_count(), 101);
+    }
+
+    #[test]
+    fn test_stream_shared() {
+        let test = TestStream::from_memory((0..=255).collect());
+        let mut first = StreamAdapter::with_buffer_size(&test, 0);
+        let mut second = StreamAdapter::with_buffer_size(&test, 0);
+        let mut buf = [0u8; 4];
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        // unbuffered adapters share the position of the stream
+        second.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [4, 5, 6, 7]);
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn test_stream_shared_buffered() {
+        let test = TestStream::from_memory((0..=255).collect());
+        let mut buf = [0u8; 4];
+        let mut first = StreamAdapter::with_buffer_size(&test, 16);
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        first.write_all(&[0; 2]).unwrap();
+        // dropping the first adapter hands the stream on at its logical position
+        drop(first);
+        let mut second = StreamAdapter::with_buffer_size(&test, 16);
+        second.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [6, 7, 8, 9]);
+        second.rewind().unwrap();
+        second.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        drop(second);
+
+        // the writes of the first adapter reached the stream
+        let mut third = StreamAdapter::with_buffer_size(&test, 16);
+        third.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 0, 6, 7]);
+    }
+
+    #[test]
+    fn test_stream_threads() {
+        let test = TestStream::new();
+        std::thread::scope(|scope| {
+            for i in 0..4u8 {
+                let test = &test;
+                scope.spawn(move || {
+                    let mut stream = StreamAdapter::with_buffer_size(test, 0);
+                    stream.write_all(&[i; 8]).unwrap();
+                    stream.flush().unwrap();
+                });
+            }
+        });
+        // each thread writes to the stream in a single call
+        assert_eq!(test.call_count(), 4);
+        test.seek_stream(0, SeekMode::Start).unwrap();
+        let mut data = test.read_stream(64).unwrap();
+        assert_eq!(data.len(), 32);
+        data.sort();
+        let expected: Vec<u8> = (0..4u8).flat_map(|i| [i; 8]).collect();
+        assert_eq!(data, expected);
+    }
+
+    // A stream that knows its length, but cannot seek from the end
+    struct LengthStream(TestStream, u64);
+
+    impl Stream for LengthStream {
+        fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+            self.0.read_stream(length)
+        }
+
+        fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+            match mode {
+                SeekMode::End => Err(StreamError::Other {
+                    reason: "cannot seek from the end".to_string(),
+                }),
+                _ => self.0.seek_stream(pos, mode),
+            }
+        }
+
+        fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+            self.0.write_stream(data)
+        }
+
+        fn stream_length(&self) -> StreamResult<Option<u64>> {
+            Ok(Some(self.1))
+        }
+    }
+
+    #[test]
+    fn test_stream_length() {
+        let test = LengthStream(TestStream::from_memory((0..=255).collect()), 256);
+        let mut stream = StreamAdapter::with_buffer_size(&test, 0);
+        assert_eq!(stream.seek(SeekFrom::End(-2)).unwrap(), 254);
+        let mut buf = [0u8; 2];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [254, 255]);
+        assert!(stream.seek(SeekFrom::End(-257)).is_err());
+
+        // a seek from the end within the read-ahead window does not seek the stream
+        let mut stream = StreamAdapter::with_buffer_size(&test, 1024);
+        stream.rewind().unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        let calls = test.0.call_count();
+        assert_eq!(stream.seek(SeekFrom::End(0)).unwrap(), 256);
+        assert_eq!(stream.seek(SeekFrom::Start(0)).unwrap(), 0);
+        assert_eq!(test.0.call_count(), calls);
+    }
+
+    // A stream that fails to report its length, like a host stream without stream_length
+    struct NoLengthStream(TestStream);
+
+    impl Stream for NoLengthStream {
+        fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+            self.0.read_stream(length)
+        }
+
+        fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+            self.0.seek_stream(pos, mode)
+        }
+
+        fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+            self.0.write_stream(data)
+        }
+
+        fn stream_length(&self) -> StreamResult<Option<u64>> {
+            Err(StreamError::Other {
+                reason: "NotImplementedError()".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_stream_length_error() {
+        let test = NoLengthStream(TestStream::from_memory((0..=255).collect()));
+        let mut stream = StreamAdapter::with_buffer_size(&test, 0);
+        assert_eq!(stream.seek(SeekFrom::End(-2)).unwrap(), 254);
+        let mut buf = [0u8; 2];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [254, 255]);
+    }
+
+    // Counts the stream calls made while reading the manifest store of an asset
+    fn read_calls(buffer_size: usize) -> usize {
+        let image = std::fs::read("tests/fixtures/C.jpg").unwrap();
+        let test = TestStream::from_memory(image);
+        let mut stream = StreamAdapter::with_buffer_size(&test, buffer_size);
+        crate::ManifestStoreReader::new()
+            .read("image/jpeg", &mut stream)
+            .unwrap();
+        drop(stream);
+        test.call_count()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn bench_stream_read_calls() {
+        let unbuffered = read_calls(0);
+        let buffered = read_calls(StreamAdapter::DEFAULT_BUFFER_SIZE);
+        assert!(buffered < unbuffered);
+    }
 }
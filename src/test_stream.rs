@@ -12,7 +12,10 @@
 // each license.
 
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::sync::RwLock;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    RwLock,
+};
 
 use std::io::Cursor;
 
@@ -20,23 +23,38 @@ use crate::stream::{SeekMode, Stream, StreamError, StreamResult};
 
 pub struct TestStream {
     stream: RwLock<Cursor<Vec<u8>>>,
+    flushes: AtomicUsize,
+    calls: AtomicUsize,
 }
 
 impl TestStream {
     pub fn new() -> Self {
         Self {
             stream: RwLock::new(Cursor::new(Vec::new())),
+            flushes: AtomicUsize::new(0),
+            calls: AtomicUsize::new(0),
         }
     }
     pub fn from_memory(data: Vec<u8>) -> Self {
         Self {
             stream: RwLock::new(Cursor::new(data)),
+            flushes: AtomicUsize::new(0),
+            calls: AtomicUsize::new(0),
         }
     }
+    /// Returns the number of times the stream was flushed
+    pub fn flush_count(&self) -> usize {
+        self.flushes.load(Ordering::SeqCst)
+    }
+    /// Returns the number of read, seek and write calls made on the stream
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
 }
 
 impl Stream for TestStream {
     fn read_stream(&self, length: u64) -> StreamResult<Vec<u8>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
         if let Ok(mut stream) = RwLock::write(&self.stream) {
             let mut data = vec![0u8; length as usize];
             let bytes_read = stream.read(&mut data).map_err(|e| StreamError::Io {
@@ -53,6 +71,7 @@ impl Stream for TestStream {
     }
 
     fn seek_stream(&self, pos: i64, mode: SeekMode) -> StreamResult<u64> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
         if let Ok(mut stream) = RwLock::write(&self.stream) {
             //stream.seek(SeekFrom::Start(pos as u64)).map_err(|e| StreamError::Io{ reason: e.to_string()})?;
             let whence = match mode {
@@ -71,6 +90,7 @@ impl Stream for TestStream {
     }
 
     fn write_stream(&self, data: Vec<u8>) -> StreamResult<u64> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
         if let Ok(mut stream) = RwLock::write(&self.stream) {
             let len = stream.write(&data).map_err(|e| StreamError::Io {
                 reason: e.to_string(),
@@ -82,4 +102,9 @@ impl Stream for TestStream {
             })
         }
     }
+
+    fn flush_stream(&self) -> StreamResult<()> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
 }
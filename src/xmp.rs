@@ -0,0 +1,208 @@
+// Copyright 2023 Adobe. All rights reserved.
+// This file is licensed to you under the Apache License,
+// Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+// or the MIT license (http://opensource.org/licenses/MIT),
+// at your option.
+
+// Unless required by applicable law or agreed to in writing,
+// this software is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR REPRESENTATIONS OF ANY KIND, either express or
+// implied. See the LICENSE-MIT and LICENSE-APACHE files for the
+// specific language governing permissions and limitations under
+// each license.
+
+use std::io::Write;
+
+use crate::{C2paError, Result};
+
+/// The signature that starts an XMP APP1 segment in a JPEG
+const JPEG_XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// The largest payload of a JPEG marker segment
+const JPEG_MAX_SEGMENT_SIZE: usize = 0xffff - 2;
+
+const DCTERMS_NS: &str = r#"xmlns:dcterms="http://purl.org/dc/terms/""#;
+const PROVENANCE: &str = "dcterms:provenance";
+const RDF_DESCRIPTION: &str = "<rdf:Description";
+
+/// Checks that a remote manifest reference can be written to a format
+///
+/// The c2pa sdk cannot write XMP into streams, so this is done here for
+/// the formats that support it. Only JPEG is currently supported
+pub(crate) fn check_remote_reference_format(format: &str) -> Result<()> {
+    match format.to_lowercase().as_str() {
+        "jpg" | "jpeg" | "image/jpeg" => Ok(()),
+        _ => Err(C2paError::NotSupported(format!(
+            "remote manifest references cannot be written to {format}"
+        ))),
+    }
+}
+
+/// Writes a copy of an asset with a remote manifest reference in its XMP
+///
+/// The whole asset is read into memory to rewrite its XMP segment,
+/// and unsupported formats fail before anything is read
+/// # Arguments
+/// * `format` - the format of the asset
+/// * `url` - the url of the remote manifest
+/// * `input` - the asset to copy
+/// * `output` - the stream to write the updated asset to
+///
+pub(crate) fn embed_remote_reference(
+    format: &str,
+    url: &str,
+    input: &mut dyn c2pa::CAIRead,
+    output: &mut dyn Write,
+) -> Result<()> {
+    check_remote_reference_format(format)?;
+    let mut asset = Vec::new();
+    input.rewind().map_err(C2paError::from)?;
+    input.read_to_end(&mut asset).map_err(C2paError::from)?;
+    let asset = jpeg_set_provenance(&asset, url)?;
+    output.write_all(&asset).map_err(C2paError::from)
+}
+
+// Returns a copy of a JPEG with the provenance set in its XMP segment,
+// adding an XMP segment after any JFIF and Exif segments if needed
+fn jpeg_set_provenance(jpeg: &[u8], url: &str) -> Result<Vec<u8>> {
+    let invalid = || C2paError::Decoding("invalid JPEG".to_string());
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(invalid());
+    }
+    let mut insert_at = 2;
+    let mut xmp_segment = None;
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xff {
+        let marker = jpeg[pos + 1];
+        // stop at the start of scan or any non application segment
+        if !(0xe0..=0xef).contains(&marker) {
+            break;
+        }
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > jpeg.len() {
+            return Err(invalid());
+        }
+        let data = &jpeg[pos + 4..end];
+        match marker {
+            0xe1 if data.starts_with(JPEG_XMP_SIGNATURE) => {
+                xmp_segment = Some((pos, end));
+                break;
+            }
+            0xe0 | 0xe1 => insert_at = end,
+            _ => (),
+        }
+        pos = end;
+    }
+
+    let (start, end, xmp) = match xmp_segment {
+        Some((start, end)) => {
+            let xmp = String::from_utf8_lossy(&jpeg[start + 4 + JPEG_XMP_SIGNATURE.len()..end]);
+            (start, end, set_provenance(&xmp, url)?)
+        }
+        None => (insert_at, insert_at, new_xmp(url)),
+    };
+
+    let len = 2 + JPEG_XMP_SIGNATURE.len() + xmp.len();
+    if len > JPEG_MAX_SEGMENT_SIZE {
+        return Err(C2paError::Encoding("XMP segment too large".to_string()));
+    }
+    let mut output = Vec::with_capacity(jpeg.len() + len + 2);
+    output.extend_from_slice(&jpeg[..start]);
+    output.extend_from_slice(&[0xff, 0xe1]);
+    output.extend_from_slice(&(len as u16).to_be_bytes());
+    output.extend_from_slice(JPEG_XMP_SIGNATURE);
+    output.extend_from_slice(xmp.as_bytes());
+    output.extend_from_slice(&jpeg[end..]);
+    Ok(output)
+}
+
+// Creates a minimal XMP packet holding only the provenance
+fn new_xmp(url: &str) -> String {
+    format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" {DCTERMS_NS} {PROVENANCE}="{}"/></rdf:RDF></x:xmpmeta><?xpacket end="w"?>"#,
+        escape_attribute(url)
+    )
+}
+
+// Sets the provenance in an existing XMP packet, replacing any previous value
+fn set_provenance(xmp: &str, url: &str) -> Result<String> {
+    let url = escape_attribute(url);
+    let attribute = format!("{PROVENANCE}=\"");
+    if let Some(start) = xmp.find(&attribute).map(|i| i + attribute.len()) {
+        if let Some(len) = xmp[start..].find('"') {
+            return Ok(format!("{}{url}{}", &xmp[..start], &xmp[start + len..]));
+        }
+    }
+    let element = format!("<{PROVENANCE}>");
+    if let Some(start) = xmp.find(&element).map(|i| i + element.len()) {
+        if let Some(len) = xmp[start..].find("</") {
+            return Ok(format!("{}{url}{}", &xmp[..start], &xmp[start + len..]));
+        }
+    }
+    let start = xmp
+        .find(RDF_DESCRIPTION)
+        .map(|i| i + RDF_DESCRIPTION.len())
+        .ok_or_else(|| C2paError::Decoding("XMP has no rdf:Description".to_string()))?;
+    let namespace = if xmp.contains("xmlns:dcterms=") {
+        String::new()
+    } else {
+        format!(" {DCTERMS_NS}")
+    };
+    Ok(format!(
+        "{}{namespace} {PROVENANCE}=\"{url}\"{}",
+        &xmp[..start],
+        &xmp[start..]
+    ))
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/manifest.c2pa";
+
+    #[test]
+    fn test_set_provenance() {
+        let xmp = r#"<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=""/></rdf:RDF></x:xmpmeta>"#;
+        let xmp = set_provenance(xmp, URL).unwrap();
+        assert!(xmp.contains(&format!("{PROVENANCE}=\"{URL}\"")));
+        assert!(xmp.contains(DCTERMS_NS));
+
+        // replaces an existing value
+        let xmp = set_provenance(&xmp, "https://example.com/other.c2pa").unwrap();
+        assert!(!xmp.contains(URL));
+        assert_eq!(xmp.matches(PROVENANCE).count(), 1);
+    }
+
+    #[test]
+    fn test_remote_reference_format() {
+        assert!(check_remote_reference_format("image/jpeg").is_ok());
+        assert!(check_remote_reference_format("JPG").is_ok());
+        // unsupported formats fail without reading the asset
+        let mut input = std::io::Cursor::new(Vec::new());
+        let mut output = Vec::new();
+        let result = embed_remote_reference("image/png", URL, &mut input, &mut output);
+        assert!(matches!(result, Err(C2paError::NotSupported(_))));
+        assert_eq!(input.position(), 0);
+    }
+
+    #[test]
+    fn test_jpeg_set_provenance() {
+        let jpeg = include_bytes!("../tests/fixtures/A.jpg");
+        let output = jpeg_set_provenance(jpeg, URL).unwrap();
+        let xmp = String::from_utf8_lossy(&output);
+        assert!(xmp.contains(&format!("{PROVENANCE}=\"{URL}\"")));
+        // adding the reference again replaces the segment
+        let output2 = jpeg_set_provenance(&output, URL).unwrap();
+        assert_eq!(output, output2);
+    }
+}
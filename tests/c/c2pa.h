@@ -30,14 +30,58 @@
     #define IMPORT
 #endif
 
+/**
+ * A stable code for each Error variant, for callers that cannot match on the enum
+ */
+typedef enum C2paErrorCode {
+  C2paErrorCode_None = 0,
+  C2paErrorCode_Assertion = 1,
+  C2paErrorCode_AssertionNotFound = 2,
+  C2paErrorCode_Decoding = 3,
+  C2paErrorCode_Encoding = 4,
+  C2paErrorCode_FileNotFound = 5,
+  C2paErrorCode_Io = 6,
+  C2paErrorCode_Json = 7,
+  C2paErrorCode_Manifest = 8,
+  C2paErrorCode_ManifestNotFound = 9,
+  C2paErrorCode_NotSupported = 10,
+  C2paErrorCode_Other = 11,
+  C2paErrorCode_NullParameter = 12,
+  C2paErrorCode_RemoteManifest = 13,
+  C2paErrorCode_ResourceNotFound = 14,
+  C2paErrorCode_RwLock = 15,
+  C2paErrorCode_Signature = 16,
+  C2paErrorCode_Verify = 17,
+  C2paErrorCode_Panic = 18,
+} C2paErrorCode;
+
+/**
+ * The relationship of an ingredient to the asset it is added to
+ */
+typedef enum Relationship {
+  ParentOf,
+  ComponentOf,
+  InputTo,
+} Relationship;
+
 typedef enum SeekMode {
   Start = 0,
   End = 1,
   Current = 2,
 } SeekMode;
 
+/**
+ * A memory stream for C, whose contents can be read back after signing
+ *
+ * This is a separate handle from C2paStream, so that the contents can only be
+ * read from a stream that is known to be a memory stream
+ */
+typedef struct C2paMemoryStream C2paMemoryStream;
+
 typedef struct C2paSigner C2paSigner;
 
+typedef struct IngredientBuilder IngredientBuilder;
+
 typedef struct ManifestBuilder ManifestBuilder;
 
 /**
@@ -54,6 +98,16 @@ typedef intptr_t (*SignerCallback)(uint8_t *data,
                                    uint8_t *signature,
                                    intptr_t sig_max_size);
 
+/**
+ * Configuration settings for the IngredientBuilder
+ */
+typedef struct IngredientBuilderSettingsC {
+  const char *title;
+  const char *format;
+  const char *instance_id;
+  enum Relationship relationship;
+} IngredientBuilderSettingsC;
+
 /**
  * Defines the configuration for a Signer
  *
@@ -93,6 +147,47 @@ typedef struct StreamContext {
 
 } StreamContext;
 
+/**
+ * An Opaque struct to hold a context value for the signer callback
+ */
+typedef struct SignerContext {
+
+} SignerContext;
+
+/**
+ * Defines a callback to sign data with a context
+ *
+ * Returns the size of the signature, or a negative value on error
+ */
+typedef intptr_t (*SignerContextCallback)(const struct SignerContext *context,
+                                          const uint8_t *data,
+                                          uintptr_t len,
+                                          uint8_t *signature,
+                                          uintptr_t sig_max_size);
+
+/**
+ * Defines a callback to release the context of a signer
+ */
+typedef void (*SignerContextRelease)(const struct SignerContext *context);
+
+/**
+ * An Opaque struct to hold a context value for the fetch callback
+ */
+typedef struct FetchContext {
+
+} FetchContext;
+
+/**
+ * Defines a callback to fetch a remote manifest store
+ *
+ * Returns the size of the manifest store, or a negative value on error.
+ * If the size is larger than len, the callback is called again with a large enough buffer
+ */
+typedef intptr_t (*FetchCallback)(const struct FetchContext *context,
+                                  const char *url,
+                                  uint8_t *data,
+                                  uintptr_t len);
+
 /**
  * Defines a callback to read from a stream
  */
@@ -100,6 +195,11 @@ typedef intptr_t (*ReadCallback)(const struct StreamContext *context, uint8_t *d
 
 /**
  * Defines a callback to seek to an offset in a stream
+ *
+ * Returns the new position in the stream, or a negative value on error.
+ * Streams are read in place, so the position must be correct.
+ * Offsets and positions are limited to c_long and c_int, use a Seek64Callback
+ * for streams over 2 GiB
  */
 typedef int (*SeekCallback)(const struct StreamContext *context, long offset, enum SeekMode mode);
 
@@ -110,6 +210,38 @@ typedef intptr_t (*WriteCallback)(const struct StreamContext *context,
                                   const uint8_t *data,
                                   uintptr_t len);
 
+/**
+ * Defines a callback to flush any buffered writes of a stream
+ *
+ * Returns 0 if successful, or a negative value on error
+ */
+typedef int (*FlushCallback)(const struct StreamContext *context);
+
+/**
+ * Defines a callback to close a stream when it is released
+ *
+ * Returns 0 if successful, or a negative value on error
+ */
+typedef int (*CloseCallback)(const struct StreamContext *context);
+
+/**
+ * Defines a callback to get the length of a stream without seeking
+ *
+ * Returns the length, or a negative value if it is not known,
+ * in which case the length is found by seeking to the end
+ */
+typedef int64_t (*LengthCallback)(const struct StreamContext *context);
+
+/**
+ * Defines a callback to seek to a 64 bit offset in a stream
+ *
+ * Returns the new position in the stream, or a negative value on error.
+ * Streams are read in place, so the position must be correct
+ */
+typedef int64_t (*Seek64Callback)(const struct StreamContext *context,
+                                  int64_t offset,
+                                  enum SeekMode mode);
+
 /**
  * A C2paStream is a Rust Read/Write/Seek stream that can be used in C
  */
@@ -118,24 +250,140 @@ typedef struct C2paStream {
   ReadCallback read_callback;
   SeekCallback seek_callback;
   WriteCallback write_callback;
+  FlushCallback flush_callback;
+  CloseCallback close_callback;
+  LengthCallback length_callback;
+  Seek64Callback seek64_callback;
 } C2paStream;
 
+/**
+ * The callbacks of a C2paStream created by c2pa_create_stream_with_callbacks
+ *
+ * The read, seek and write callbacks are required, the others may be NULL
+ */
+typedef struct C2paStreamCallbacks {
+  /**
+   * Reads from the stream
+   */
+  ReadCallback read;
+  /**
+   * Seeks in the stream with 64 bit offsets
+   */
+  Seek64Callback seek;
+  /**
+   * Writes to the stream
+   */
+  WriteCallback write;
+  /**
+   * Called when signing has written the output
+   */
+  FlushCallback flush;
+  /**
+   * Called when the stream is released
+   */
+  CloseCallback close;
+  /**
+   * Used instead of seeking to the end to find the length of the stream
+   */
+  LengthCallback length;
+} C2paStreamCallbacks;
+
+/**
+ * Configuration settings for the ManifestStoreReader
+ */
+typedef struct ManifestStoreReaderSettingsC {
+  /**
+   * The maximum size in bytes of a stream that will be read, 0 for no limit
+   */
+  uint64_t max_stream_size;
+} ManifestStoreReaderSettingsC;
+
 /**
  * Configuration settings for the ManifestBuilder
  * this is mostly a placeholder for future expansion
  */
 typedef struct ManifestBuilderSettingsC {
   const char *claim_generator;
+  /**
+   * If set, signing writes a manifest store (.c2pa) to the output
+   * and leaves the asset unmodified
+   */
+  bool sidecar;
 } ManifestBuilderSettingsC;
 
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
 
+/**
+ * Creates a signer that calls a signing callback
+ *
+ * # Arguments
+ * * `signer` - the callback that signs data, returning the signature size or a negative value on error
+ * * `config` - a pointer to a SignerConfigC
+ *
+ * # Returns
+ * * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The returned value MUST be released by calling c2pa_release_signer
+ * The signer can be used by any number of builders and threads until it is released
+ *
+ */
 IMPORT extern
 struct C2paSigner *c2pa_create_signer(SignerCallback signer,
                                       const struct SignerConfigC *config);
 
+/**
+ * Creates a signer with a context that is passed to every call of the callback
+ *
+ * # Arguments
+ * * `context` - an opaque pointer to the state of the signer, such as a key handle
+ * * `signer` - the callback that signs data, returning the signature size or a negative value on error
+ * * `release` - an optional callback to release the context when the signer is dropped, may be NULL
+ * * `config` - a pointer to a SignerConfigC
+ *
+ * # Returns
+ * * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The context must remain valid, and usable from any thread, for the lifetime of the signer
+ * If the signer cannot be created the release callback is called before returning
+ * The returned value MUST be released by calling c2pa_release_signer,
+ * which then calls the release callback
+ *
+ */
+IMPORT extern
+struct C2paSigner *c2pa_create_signer_with_context(const struct SignerContext *context,
+                                                   SignerContextCallback signer,
+                                                   SignerContextRelease release,
+                                                   const struct SignerConfigC *config);
+
+/**
+ * Creates a signer that signs with a private key, without a callback
+ *
+ * # Arguments
+ * * `alg` - the signing algorithm, one of es256, es384, es512, ps256, ps384, ps512 or ed25519
+ * * `certs` - the PEM encoded certificate chain, starting with the signing certificate
+ * * `private_key` - the PEM encoded private key
+ * * `tsa_url` - an optional url of a time stamp authority, may be NULL
+ *
+ * # Returns
+ * * a pointer to a C2paSigner, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The returned value MUST be released by calling c2pa_release_signer
+ *
+ */
+IMPORT extern
+struct C2paSigner *c2pa_create_signer_from_keys(const char *alg,
+                                                const char *certs,
+                                                const char *private_key,
+                                                const char *tsa_url);
+
 /**
  * Returns the last error message
  *
@@ -145,6 +393,45 @@ struct C2paSigner *c2pa_create_signer(SignerCallback signer,
  */
 IMPORT extern char *c2pa_error(void);
 
+/**
+ * Returns the code of the last error
+ *
+ * The codes are stable and match the variants of the error enum,
+ * C2paErrorCode_None is returned if there is no error
+ */
+IMPORT extern C2paErrorCode c2pa_error_code(void);
+
+/**
+ * Returns a json description of the last error
+ *
+ * This holds the code, variant and message of the error,
+ * along with any c2pa validation codes that describe it,
+ * and the code passed to c2pa_set_callback_error by the callback that caused it, or null
+ *
+ * # Safety
+ * The returned value MUST be released by calling release_string
+ * and it is no longer valid after that call.
+ * Returns NULL if there is no error
+ */
+IMPORT extern char *c2pa_error_details_json(void);
+
+/**
+ * Reports the cause of a failure from a C callback
+ *
+ * A stream, signer or fetch callback calls this before returning a negative value,
+ * and the message is then included in the error returned by c2pa_error.
+ * The code is reported as callback_code by c2pa_error_details_json
+ *
+ * # Arguments
+ * * `code` - an error code defined by the caller, such as an errno value
+ * * `message` - a null terminated description of the error, may be NULL
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern void c2pa_set_callback_error(int code, const char *message);
+
 /**
  * Returns a version string for logging
  *
@@ -185,6 +472,212 @@ struct C2paStream *c2pa_create_stream(struct StreamContext *context,
                                       SeekCallback seek,
                                       WriteCallback write);
 
+/**
+ * Creates a new C2paStream from context with callbacks, including flush and close
+ *
+ * # Arguments
+ * * `context` - a pointer to a StreamContext
+ * * `read` - a ReadCallback to read from the stream
+ * * `seek` - a SeekCallback to seek in the stream
+ * * `write` - a WriteCallback to write to the stream
+ * * `flush` - an optional FlushCallback, called when signing has written the output, may be NULL
+ * * `close` - an optional CloseCallback, called when the stream is released, may be NULL
+ *
+ * # Safety
+ * The context must remain valid for the lifetime of the C2paStream
+ * The resulting C2paStream must be released by calling c2pa_release_stream,
+ * a close error is then available from c2pa_error
+ *
+ */
+IMPORT extern
+struct C2paStream *c2pa_create_stream_ex(struct StreamContext *context,
+                                         ReadCallback read,
+                                         SeekCallback seek,
+                                         WriteCallback write,
+                                         FlushCallback flush,
+                                         CloseCallback close);
+
+/**
+ * Creates a new C2paStream from context with a set of callbacks
+ *
+ * Unlike c2pa_create_stream, the stream seeks with 64 bit offsets,
+ * so it can be used for streams over 2 GiB
+ *
+ * # Arguments
+ * * `context` - a pointer to a StreamContext
+ * * `callbacks` - the callbacks of the stream, which are copied
+ *
+ * # Returns
+ * * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * The context must remain valid for the lifetime of the C2paStream
+ * The resulting C2paStream must be released by calling c2pa_release_stream,
+ * a close error is then available from c2pa_error
+ *
+ */
+IMPORT extern
+struct C2paStream *c2pa_create_stream_with_callbacks(struct StreamContext *context,
+                                                     const struct C2paStreamCallbacks *callbacks);
+
+/**
+ * Sets the callback used to find the length of a stream
+ *
+ * # Arguments
+ * * `stream` - a pointer to a C2paStream
+ * * `length` - a LengthCallback, used instead of seeking to the end
+ *   to find the length of the stream, or NULL to always seek
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * The stream must be a valid C2paStream, created by one of the c2pa_create_stream functions
+ *
+ */
+IMPORT extern
+int c2pa_stream_set_length_callback(struct C2paStream *stream, LengthCallback length);
+
+/**
+ * Creates a new write-only C2paStream from context with callbacks
+ *
+ * This allows signing into a pipe, socket or response body with
+ * c2pa_manifest_builder_sign_sequential, which only writes and flushes the output
+ *
+ * # Arguments
+ * * `context` - a pointer to a StreamContext
+ * * `write` - a WriteCallback to write to the stream
+ * * `flush` - an optional FlushCallback, called when signing has written the output, may be NULL
+ * * `close` - an optional CloseCallback, called when the stream is released, may be NULL
+ *
+ * # Safety
+ * The context must remain valid for the lifetime of the C2paStream
+ * The resulting C2paStream must be released by calling c2pa_release_stream
+ *
+ */
+IMPORT extern
+struct C2paStream *c2pa_create_write_stream(struct StreamContext *context,
+                                            WriteCallback write,
+                                            FlushCallback flush,
+                                            CloseCallback close);
+
+/**
+ * Opens a C2paStream over a file, implemented in Rust
+ *
+ * The stream reads and writes the file directly, without any C callbacks
+ *
+ * # Arguments
+ * * `path` - the path of the file
+ * * `mode` - an fopen style mode: "r", "w", "a", optionally with "+" and "b"
+ *
+ * # Returns
+ * * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The resulting C2paStream must be released by calling c2pa_release_stream,
+ * which closes the file
+ *
+ */
+IMPORT extern struct C2paStream *c2pa_open_file_stream(const char *path, const char *mode);
+
+/**
+ * Creates a memory stream, implemented in Rust
+ *
+ * The stream starts with a copy of the data and is positioned at the start.
+ * It is used as a C2paStream through c2pa_memory_stream_as_stream,
+ * so it can be written to by signing and its contents read back with c2pa_memory_stream_data
+ *
+ * # Arguments
+ * * `data` - the initial contents of the stream, may be NULL if len is 0
+ * * `len` - the size of the data
+ *
+ * # Returns
+ * * a pointer to a C2paMemoryStream, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * The data must be valid for len bytes
+ * The resulting C2paMemoryStream must be released by calling c2pa_release_memory_stream
+ *
+ */
+IMPORT extern struct C2paMemoryStream *c2pa_create_memory_stream(const uint8_t *data, uintptr_t len);
+
+/**
+ * Returns the C2paStream of a memory stream, to read, sign or write to
+ *
+ * # Returns
+ * * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * The C2paStream belongs to the memory stream, it is only valid until
+ * the memory stream is released and must not be released itself
+ *
+ */
+IMPORT extern struct C2paStream *c2pa_memory_stream_as_stream(struct C2paMemoryStream *memory);
+
+/**
+ * Creates a seekable C2paStream over a forward-only source
+ *
+ * The source can only be read from start to end, such as an upload being received.
+ * It is only read as far as needed. What has been read is kept in memory
+ * up to the threshold, and then moved to a temporary file that is deleted with the stream
+ *
+ * # Arguments
+ * * `context` - a pointer to a StreamContext
+ * * `read` - a ReadCallback to read from the source
+ * * `close` - an optional CloseCallback, called when the stream is released, may be NULL
+ * * `threshold` - the number of bytes to keep in memory before spooling to a file
+ *
+ * # Returns
+ * * a pointer to a C2paStream, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * The context must remain valid for the lifetime of the C2paStream.
+ * Once the context and read callback have been checked, the context belongs
+ * to the spool stream: if this call fails after that, the close callback is called.
+ * The resulting C2paStream must be released by calling c2pa_release_stream
+ *
+ */
+IMPORT extern
+struct C2paStream *c2pa_create_spool_stream(struct StreamContext *context,
+                                            ReadCallback read,
+                                            CloseCallback close,
+                                            uint64_t threshold);
+
+/**
+ * Copies the contents of a memory stream into a buffer
+ *
+ * Call with a NULL buffer to get the size of the contents
+ *
+ * # Arguments
+ * * `memory` - a C2paMemoryStream created by c2pa_create_memory_stream
+ * * `buffer` - the buffer to copy into, may be NULL if len is 0
+ * * `len` - the size of the buffer
+ *
+ * # Returns
+ * * the size of the contents, which is more than len if the buffer was too small,
+ *   or a negative value with the error available from c2pa_error
+ *
+ * # Safety
+ * The buffer must be valid for len bytes
+ *
+ */
+IMPORT extern intptr_t c2pa_memory_stream_data(const struct C2paMemoryStream *memory,
+                                               uint8_t *buffer,
+                                               uintptr_t len);
+
+/**
+ * Detects the format of an asset from the content of a stream
+ *
+ * # Returns
+ * The mime type of the asset or NULL if the format could not be detected
+ *
+ * # Safety
+ * The returned value MUST be released by calling release_string
+ * and it is no longer valid after that call.
+ */
+IMPORT extern char *c2pa_detect_format(struct C2paStream *stream);
+
 /**
  * Verify a stream and return a ManifestStore report
  *
@@ -211,12 +704,47 @@ IMPORT extern char *c2pa_verify_stream(struct C2paStream *reader);
  */
 IMPORT extern struct ManifestStoreReader *c2pa_manifest_reader_new(void);
 
+/**
+ * Create a new ManifestStoreReader with settings
+ *
+ * # Arguments
+ * * `settings` - a pointer to a ManifestStoreReaderSettingsC
+ *
+ * # Safety
+ * The returned value MUST be released by calling release_manifest_reader
+ */
+IMPORT extern
+struct ManifestStoreReader *c2pa_manifest_reader_with_settings(const struct ManifestStoreReaderSettingsC *settings);
+
+/**
+ * Sets the callback used to fetch remote manifest stores
+ *
+ * # Arguments
+ * * `reader_ptr` - a pointer to a ManifestStoreReader
+ * * `context` - a context pointer passed to each call of the callback
+ * * `fetch` - the callback that copies the manifest store for a url into a buffer
+ *
+ * The callback returns the size of the manifest store, or a negative value on error.
+ * If the size is larger than the buffer, it is called again with a large enough buffer
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * The context must remain valid, and usable from any thread, for the lifetime of the reader
+ *
+ */
+IMPORT extern
+int c2pa_manifest_reader_set_fetcher(struct ManifestStoreReader **reader_ptr,
+                                     const struct FetchContext *context,
+                                     FetchCallback fetch);
+
 /**
  * Read a manifest store from a stream
  *
  * # Arguments
  * * `reader_ptr` - a pointer to a ManifestStoreReader
- * * `format` - the format of the manifest store
+ * * `format` - the format of the manifest store, empty or "auto" to detect it
  * * `stream` - the stream to read from
  *
  * # Returns
@@ -243,6 +771,30 @@ char *c2pa_manifest_reader_read(struct ManifestStoreReader **reader_ptr,
                                 const char *format,
                                 struct C2paStream *stream);
 
+/**
+ * Read a sidecar manifest store and validate it against an asset stream
+ *
+ * # Arguments
+ * * `reader_ptr` - a pointer to a ManifestStoreReader
+ * * `format` - the format of the asset, empty or "auto" to detect it
+ * * `manifest_stream` - the stream to read the manifest store (.c2pa) from
+ * * `asset_stream` - the stream to read the asset from
+ *
+ * # Returns
+ * * `Result<String>` - the json representation of the manifest store
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The returned value MUST be released by calling release_string
+ * and it is no longer valid after that call.
+ *
+ */
+IMPORT extern
+char *c2pa_manifest_reader_read_with_manifest(struct ManifestStoreReader **reader_ptr,
+                                              const char *format,
+                                              struct C2paStream *manifest_stream,
+                                              struct C2paStream *asset_stream);
+
 /**
  * Writes a resource from the manifest reader to a stream
  *
@@ -275,6 +827,94 @@ void c2pa_manifest_reader_resource(struct ManifestStoreReader **reader_ptr,
                                    const char *id,
                                    struct C2paStream *stream);
 
+/**
+ * Create a new IngredientBuilder
+ *
+ * # Arguments
+ * * `settings` - a pointer to an IngredientBuilderSettingsC
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The returned value MUST be released by calling release_ingredient_builder
+ *
+ */
+IMPORT extern
+struct IngredientBuilder *c2pa_create_ingredient_builder(const struct IngredientBuilderSettingsC *settings);
+
+/**
+ * Replaces the ingredient of an IngredientBuilder from an ingredient definition
+ *
+ * Resources of the current ingredient are kept when the definition still refers to them
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to an IngredientBuilder
+ * * `json` - a pointer to a null terminated JSON ingredient definition
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern
+int c2pa_ingredient_builder_from_json(struct IngredientBuilder **builder_ptr, const char *json);
+
+/**
+ * Read an ingredient from a stream
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to an IngredientBuilder
+ * * `format` - the format of the asset, empty or "auto" to detect it
+ * * `stream` - the stream to read from
+ *
+ * # Returns
+ * * the json representation of the ingredient, or NULL with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ * The returned value MUST be released by calling release_string
+ * and it is no longer valid after that call.
+ *
+ */
+IMPORT extern
+char *c2pa_ingredient_builder_read(struct IngredientBuilder **builder_ptr,
+                                   const char *format,
+                                   struct C2paStream *stream);
+
+/**
+ * Returns the json representation of an ingredient
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to an IngredientBuilder
+ *
+ * # Safety
+ * The returned value MUST be released by calling release_string
+ * and it is no longer valid after that call.
+ *
+ */
+IMPORT extern char *c2pa_ingredient_builder_json(struct IngredientBuilder **builder_ptr);
+
+/**
+ * Writes a resource of an ingredient to a stream
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to an IngredientBuilder
+ * * `id` - the resource identifier
+ * * `stream` - the stream to write the resource to
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern
+int c2pa_ingredient_builder_resource(struct IngredientBuilder **builder_ptr,
+                                     const char *id,
+                                     struct C2paStream *stream);
+
 /**
  * Create a ManifestBuilder
  *
@@ -298,6 +938,7 @@ void c2pa_manifest_reader_resource(struct ManifestStoreReader **reader_ptr,
  * }"#;
  * let settings = ManifestBuilderSettings {
  *    generator: "test".to_string(),
+ *    sidecar: false,
  * };
  *
  *   let builder = ManifestBuilder::new(&settings);
@@ -309,6 +950,70 @@ IMPORT extern
 struct ManifestBuilder *c2pa_create_manifest_builder(const struct ManifestBuilderSettingsC *settings,
                                                      const char *json);
 
+/**
+ * Adds a resource to a ManifestBuilder from a stream
+ *
+ * The resource is read to the end and stored as raw bytes under its identifier.
+ * Its mime type comes from the manifest definition that references the identifier,
+ * such as the format of a thumbnail or icon
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to a ManifestBuilder
+ * * `id` - the resource identifier referenced in the manifest definition
+ * * `stream` - the stream to read the resource from
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_add_resource(struct ManifestBuilder **builder_ptr,
+                                       const char *id,
+                                       struct C2paStream *stream);
+
+/**
+ * Adds an ingredient read by an IngredientBuilder to a ManifestBuilder
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to a ManifestBuilder
+ * * `ingredient` - a pointer to an IngredientBuilder
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * The ingredient must be a valid IngredientBuilder, it is not released by this call
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_add_ingredient(struct ManifestBuilder **builder_ptr,
+                                         const struct IngredientBuilder *ingredient);
+
+/**
+ * Adds an ingredient to a ManifestBuilder from a stream
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to a ManifestBuilder
+ * * `json` - an ingredient definition, with fields that replace those read from the stream
+ * * `format` - the format of the ingredient, empty or "auto" to detect it
+ * * `stream` - the stream to read the ingredient from
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_add_ingredient_stream(struct ManifestBuilder **builder_ptr,
+                                                const char *json,
+                                                const char *format,
+                                                struct C2paStream *stream);
+
 /**
  * Sign using a ManifestBuilder
  *
@@ -316,7 +1021,13 @@ struct ManifestBuilder *c2pa_create_manifest_builder(const struct ManifestBuilde
  * * `builder` - a pointer to a ManifestBuilder
  * * `signer` - a pointer to a C2paSigner
  * * `input` - a pointer to a C2paStream
- * * `output` - optional pointer to a C2paStream
+ * * `output` - a pointer to a C2paStream to write the signed asset to, must not be NULL
+ *
+ * If the builder was created with the sidecar setting, the output receives
+ * the manifest store (.c2pa) and the asset is not modified
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
  *
  */
 IMPORT extern
@@ -325,6 +1036,86 @@ int c2pa_manifest_builder_sign(struct ManifestBuilder **builder_ptr,
                                struct C2paStream *input,
                                struct C2paStream *output);
 
+/**
+ * Sign using a ManifestBuilder, writing the signed asset sequentially
+ *
+ * The output is only written to and flushed, never read or seeked,
+ * so it can be created with c2pa_create_write_stream.
+ * The signed asset is staged in a temporary file before it is written.
+ * The input must be seekable, a forward-only input can be read through c2pa_create_spool_stream
+ *
+ * # Arguments
+ * * `builder` - a pointer to a ManifestBuilder
+ * * `signer` - a pointer to a C2paSigner
+ * * `input` - a pointer to a seekable C2paStream
+ * * `output` - a pointer to a C2paStream, which may be write-only
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * All streams must be valid C2paStreams
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_sign_sequential(struct ManifestBuilder **builder_ptr,
+                                          const struct C2paSigner *signer,
+                                          struct C2paStream *input,
+                                          struct C2paStream *output);
+
+/**
+ * Sets a remote url for the manifest store of a ManifestBuilder
+ *
+ * # Arguments
+ * * `builder_ptr` - a pointer to a ManifestBuilder
+ * * `url` - the url where the manifest store will be available
+ * * `remote_only` - if true, the manifest is not embedded in the asset
+ *
+ * The url is written to the XMP of the signed asset. Use
+ * c2pa_manifest_builder_sign_with_manifest to get the manifest store to upload
+ *
+ * Only JPEG assets are currently supported: signing other formats fails
+ * with a NotSupported error, and the asset is held in memory while the
+ * reference is written to its XMP
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * Reads from null terminated C strings
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_set_remote_url(struct ManifestBuilder **builder_ptr,
+                                         const char *url,
+                                         bool remote_only);
+
+/**
+ * Sign using a ManifestBuilder, writing the manifest store to a stream
+ *
+ * # Arguments
+ * * `builder` - a pointer to a ManifestBuilder
+ * * `signer` - a pointer to a C2paSigner
+ * * `input` - a pointer to a C2paStream
+ * * `output` - a pointer to a C2paStream
+ * * `manifest_output` - a pointer to a C2paStream to receive the manifest store
+ *
+ * The manifest store is what must be uploaded when a remote url is set
+ *
+ * # Returns
+ * * `0` if successful, otherwise `-1` with the error available from c2pa_error
+ *
+ * # Safety
+ * All streams must be valid C2paStreams
+ *
+ */
+IMPORT extern
+int c2pa_manifest_builder_sign_with_manifest(struct ManifestBuilder **builder_ptr,
+                                             const struct C2paSigner *signer,
+                                             struct C2paStream *input,
+                                             struct C2paStream *output,
+                                             struct C2paStream *manifest_output);
+
 /**
  * Releases a string allocated by Rust
  *
@@ -345,6 +1136,15 @@ IMPORT extern void c2pa_release_string(char *s);
  */
 IMPORT extern void c2pa_release_stream(struct C2paStream *stream);
 
+/**
+ * Releases a C2paMemoryStream allocated by Rust
+ *
+ * # Safety
+ * can only be released once and is invalid after this call,
+ * along with the C2paStream returned by c2pa_memory_stream_as_stream
+ */
+IMPORT extern void c2pa_release_memory_stream(struct C2paMemoryStream *memory);
+
 /**
  * Releases a ManifestStoreReader allocated by Rust
  *
@@ -361,6 +1161,23 @@ IMPORT extern void c2pa_release_manifest_reader(struct ManifestStoreReader *read
  */
 IMPORT extern void c2pa_release_manifest_builder(struct ManifestBuilder *builder);
 
+/**
+ * Releases an IngredientBuilder allocated by Rust
+ *
+ * # Safety
+ * can only be released once and is invalid after this call
+ */
+IMPORT extern void c2pa_release_ingredient_builder(struct IngredientBuilder *builder);
+
+/**
+ * Releases a C2paSigner allocated by Rust
+ *
+ * # Safety
+ * can only be released once and is invalid after this call
+ * The signer must not be in use by another thread
+ */
+IMPORT extern void c2pa_release_signer(struct C2paSigner *signer);
+
 #ifdef __cplusplus
 } // extern "C"
 #endif // __cplusplus
@@ -15,6 +15,9 @@
 #include <stdlib.h>
 #include <string.h>
 #include <errno.h>
+#include <pthread.h>
+#include <limits.h>
+#include <sys/stat.h>
 #include "c2pa.h"
 
 const char * asset_path = "tests/fixtures/A.jpg";
@@ -64,10 +67,16 @@ int save_file(char* filename, uint8_t*data, size_t len) {
 
 ssize_t reader(size_t context, uint8_t *data, size_t len) {
     //printf("reader: context = %0lx, data = %p, len = %zu\n", context, data, len);
-    return fread(data, 1, len, (FILE*)context);
+    size_t bytes_read = fread(data, 1, len, (FILE*)context);
+    if (bytes_read < len && ferror((FILE*)context)) {
+        c2pa_set_callback_error(errno, strerror(errno));
+        return -1;
+    }
+    return bytes_read;
 }
 
-int seeker(size_t context,long int offset, SeekMode whence) {
+// Seeks with 64 bit offsets, for streams created with c2pa_create_stream_with_callbacks
+int64_t seeker(size_t context, int64_t offset, SeekMode whence) {
     switch (whence) {
         case Start:
             whence = SEEK_SET;
@@ -80,9 +89,20 @@ int seeker(size_t context,long int offset, SeekMode whence) {
             break;
     };
     //printf("seeker: context = %0lx, offset = %ld, whence = %d\n", context, offset, whence);
-    long int result = fseek((FILE*)context, offset, whence);
+    int result = fseeko((FILE*)context, (off_t)offset, whence);
     //printf("seeker: result = %ld, %s\n", result, result ? strerror(errno): "");
-    return result;
+    if (result != 0) {
+        c2pa_set_callback_error(errno, strerror(errno));
+        return -1;
+    }
+    // the seek callback returns the new position in the stream
+    return ftello((FILE*)context);
+}
+
+// Seeks with long offsets, for streams created with c2pa_create_stream
+int long_seeker(size_t context, long offset, SeekMode whence) {
+    int64_t position = seeker(context, offset, whence);
+    return position > INT_MAX ? -1 : (int)position;
 }
 
 int writer(size_t context, uint8_t *data, size_t len) {
@@ -90,9 +110,75 @@ int writer(size_t context, uint8_t *data, size_t len) {
     return fwrite(data, 1, len, (FILE*)context);
 }
 
+// Counts the flushes of streams opened with open_file_stream_ex
+int streams_flushed = 0;
+
+int flusher(size_t context) {
+    streams_flushed++;
+    return fflush((FILE*)context) == 0 ? 0 : -1;
+}
+
+int closer(size_t context) {
+    return fclose((FILE*)context) == 0 ? 0 : -1;
+}
+
+// Counts the length queries of streams opened with open_file_stream_ex
+int lengths_queried = 0;
+
+int64_t lengther(size_t context) {
+    struct stat st;
+    lengths_queried++;
+    // buffered writes must reach the file for its size to be right
+    if (fflush((FILE*)context) != 0 || fstat(fileno((FILE*)context), &st) != 0) {
+        return -1;
+    }
+    return st.st_size;
+}
+
+// Opens a stream that is flushed by signing, closed when it is released,
+// and that reports its length without seeking
+C2paStream* open_file_stream_ex(const char *path, const char* mode) {
+    FILE *file = fopen(path, mode);
+    if (file != NULL) {
+        C2paStreamCallbacks callbacks = {
+            .read = (ReadCallback)reader,
+            .seek = (Seek64Callback)seeker,
+            .write = (WriteCallback)writer,
+            .flush = (FlushCallback)flusher,
+            .close = (CloseCallback)closer,
+            .length = (LengthCallback)lengther,
+        };
+        return c2pa_create_stream_with_callbacks((StreamContext*)file, &callbacks);
+    }
+    return NULL;
+}
+
+int pipe_closer(size_t context) {
+    return pclose((FILE*)context) == 0 ? 0 : -1;
+}
+
+// Opens a write-only stream into a shell command, which cannot seek
+C2paStream* open_pipe_stream(const char *command) {
+    FILE *pipe = popen(command, "w");
+    if (pipe != NULL) {
+        return c2pa_create_write_stream((StreamContext*)pipe, (WriteCallback)writer, (FlushCallback)flusher, (CloseCallback)pipe_closer);
+    }
+    return NULL;
+}
+
+// Opens a stream over the output of a shell command, which cannot seek,
+// spooled so that it can be seeked
+C2paStream* open_spool_pipe_stream(const char *command, uint64_t threshold) {
+    FILE *pipe = popen(command, "r");
+    if (pipe != NULL) {
+        return c2pa_create_spool_stream((StreamContext*)pipe, (ReadCallback)reader, (CloseCallback)pipe_closer, threshold);
+    }
+    return NULL;
+}
+
 C2paStream* create_stream(FILE *file) {
     if (file != NULL) {
-       return c2pa_create_stream((StreamContext*)file, (ReadCallback)reader, (SeekCallback) seeker, (WriteCallback)writer);
+       return c2pa_create_stream((StreamContext*)file, (ReadCallback)reader, (SeekCallback)long_seeker, (WriteCallback)writer);
     }
     return NULL;
 }
@@ -101,7 +187,7 @@ C2paStream* open_file_stream(const char *path, const char* mode) {
     FILE *file = fopen(path, mode);
     if (file != NULL) {
         //printf("file open = %0lx\n", file);
-        return c2pa_create_stream((StreamContext*)file, (ReadCallback)reader, (SeekCallback) seeker, (WriteCallback)writer);
+        return c2pa_create_stream((StreamContext*)file, (ReadCallback)reader, (SeekCallback)long_seeker, (WriteCallback)writer);
     }
     return NULL;
 }
@@ -145,6 +231,73 @@ intptr_t signer_callback(uint8_t *data, uintptr_t len, uint8_t *signature, uintp
     return sig_len;
 }
 
+// Signer callback with a context, here the path of the private key
+intptr_t signer_context_callback(const SignerContext *context, const uint8_t *data, uintptr_t len, uint8_t *signature, uintptr_t sig_max_len) {
+    const char *key_path = (const char *)context;
+    if (save_file("target/c_data.bin", (uint8_t *)data, len) < 0) {
+        return -1;
+    }
+    char command[512];
+    snprintf(command, sizeof(command), "openssl dgst -sign %s -sha256 -out target/c_signature.sig target/c_data.bin", key_path);
+    system(command);
+
+    FILE* result_file = fopen("target/c_signature.sig", "rb");
+    if (result_file == NULL) {
+        return -1;
+    }
+    fseek(result_file, 0L, SEEK_END);
+    long sig_len = ftell(result_file);
+    rewind(result_file);
+    if (sig_len > sig_max_len) {
+        fclose(result_file);
+        return -1;
+    }
+    fread(signature, 1, sig_len, result_file);
+    fclose(result_file);
+    return sig_len;
+}
+
+// Counts the releases of signer contexts
+int contexts_released = 0;
+
+void release_context(const SignerContext *context) {
+    contexts_released++;
+}
+
+// Signs an asset into output_path with a new builder, returning 0 on success
+int sign_file(C2paSigner *signer, const char *output_path) {
+    ManifestBuilderSettingsC settings = { .claim_generator = "c_test"};
+    ManifestBuilder* builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    if (builder == NULL) {
+        return -1;
+    }
+    C2paStream* input = open_file_stream(asset_path, "rb");
+    C2paStream* output = open_file_stream(output_path, "wb");
+    int err = -1;
+    if (input != NULL && output != NULL) {
+        err = c2pa_manifest_builder_sign(&builder, signer, input, output);
+    }
+    if (input != NULL) {
+        close_file_stream(input);
+    }
+    if (output != NULL) {
+        close_file_stream(output);
+    }
+    c2pa_release_manifest_builder(builder);
+    return err;
+}
+
+typedef struct SignThreadArgs {
+    C2paSigner *signer;
+    char output_path[64];
+    int result;
+} SignThreadArgs;
+
+void *sign_thread(void *arg) {
+    SignThreadArgs *args = (SignThreadArgs *)arg;
+    args->result = sign_file(args->signer, args->output_path);
+    return NULL;
+}
 
 // Function to find the value associated with a key in a JSON string
 char* findValueByKey(const char* json, const char* key) {
@@ -222,6 +375,19 @@ int main(void) {
     close_file_stream(input_stream);
     //c2pa_release_stream(input_stream);
 
+    // the size limit is checked with the length callback instead of seeking to the end
+    ManifestStoreReaderSettingsC reader_settings = { .max_stream_size = 16 * 1024 * 1024 };
+    ManifestStoreReader* limited_reader = c2pa_manifest_reader_with_settings(&reader_settings);
+    C2paStream* length_stream = open_file_stream_ex("tests/fixtures/C.jpg", "rb");
+    char* limited_result = c2pa_manifest_reader_read(&limited_reader, "image/jpeg", length_stream);
+    if (limited_result == NULL || lengths_queried == 0) {
+        printf("error reading with a length callback = %s\n", c2pa_error());
+        return 1;
+    }
+    c2pa_release_string(limited_result);
+    c2pa_release_stream(length_stream);
+    c2pa_release_manifest_reader(limited_reader);
+
     // display the manifest store json
     printf("manifest json = %s\n", result);
 
@@ -290,7 +456,7 @@ int main(void) {
         printf("error creating input stream = %s\n", c2pa_error());
         return 1;
     }
-    C2paStream* output_stream = open_file_stream("target/c_output.jpg", "wb");
+    C2paStream* output_stream = open_file_stream_ex("target/c_output.jpg", "wb");
     if (output_stream == NULL) {
         printf("error creating output stream = %s\n", c2pa_error());
         return 1;
@@ -302,7 +468,212 @@ int main(void) {
         return 1;
     }
     close_file_stream(input_stream2);
-    close_file_stream(output_stream);
+    // the output is flushed by signing and closed by its close callback
+    c2pa_release_stream(output_stream);
+    if (streams_flushed != 1) {
+        printf("output stream was not flushed\n");
+        return 1;
+    }
     c2pa_release_manifest_builder(builder);
     printf("manifest added to: %s\n", "target/c_output.jpg" );
+
+    // sign again with a signer created from key files, without a callback
+    char * es256_certs = load_file("tests/fixtures/es256_certs.pem");
+    char * es256_key = load_file("tests/fixtures/es256_private.key");
+    if (es256_certs == NULL || es256_key == NULL) {
+        printf("error loading keys\n");
+        return 1;
+    }
+    C2paSigner* key_signer = c2pa_create_signer_from_keys("es256", es256_certs, es256_key, NULL);
+    if (key_signer == NULL) {
+        printf("error creating signer from keys = %s\n", c2pa_error());
+        return 1;
+    }
+
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    if (builder == NULL) {
+        printf("error creating manifest builder = %s\n", c2pa_error());
+        return 1;
+    }
+    input_stream2 = open_file_stream("tests/fixtures/A.jpg", "rb");
+    output_stream = open_file_stream("target/c_output_keys.jpg", "wb");
+    if (input_stream2 == NULL || output_stream == NULL) {
+        printf("error creating streams = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign(&builder, key_signer, input_stream2, output_stream);
+    if (err != 0) {
+        printf("error signing with keys = %s\n", c2pa_error());
+        return 1;
+    }
+    close_file_stream(input_stream2);
+    close_file_stream(output_stream);
+    c2pa_release_manifest_builder(builder);
+    printf("manifest added to: %s\n", "target/c_output_keys.jpg" );
+
+    // sign with a signer that is passed the key path as its context
+    C2paSigner* context_signer = c2pa_create_signer_with_context((const SignerContext*)"tests/fixtures/ps256.pem", signer_context_callback, release_context, &config);
+    if (context_signer == NULL) {
+        printf("error creating signer with context = %s\n", c2pa_error());
+        return 1;
+    }
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    if (builder == NULL) {
+        printf("error creating manifest builder = %s\n", c2pa_error());
+        return 1;
+    }
+    input_stream2 = open_file_stream("tests/fixtures/A.jpg", "rb");
+    output_stream = open_file_stream("target/c_output_context.jpg", "wb");
+    if (input_stream2 == NULL || output_stream == NULL) {
+        printf("error creating streams = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign(&builder, context_signer, input_stream2, output_stream);
+    if (err != 0) {
+        printf("error signing with context = %s\n", c2pa_error());
+        return 1;
+    }
+    close_file_stream(input_stream2);
+    close_file_stream(output_stream);
+    c2pa_release_manifest_builder(builder);
+    printf("manifest added to: %s\n", "target/c_output_context.jpg" );
+
+    // releasing the signer releases its context
+    c2pa_release_signer(context_signer);
+    if (contexts_released != 1) {
+        printf("signer context was not released\n");
+        return 1;
+    }
+
+    // create, sign and release signers repeatedly
+    for (int i = 0; i < 5; i++) {
+        C2paSigner* loop_signer = c2pa_create_signer_from_keys("es256", es256_certs, es256_key, NULL);
+        if (loop_signer == NULL) {
+            printf("error creating signer in loop = %s\n", c2pa_error());
+            return 1;
+        }
+        if (sign_file(loop_signer, "target/c_output_loop.jpg") != 0) {
+            printf("error signing in loop = %s\n", c2pa_error());
+            return 1;
+        }
+        c2pa_release_signer(loop_signer);
+    }
+    free(es256_certs);
+    free(es256_key);
+    printf("signed with %d signers\n", 5);
+
+    // sign with streams over files that are read and written in Rust
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    C2paStream* native_input = c2pa_open_file_stream(asset_path, "rb");
+    C2paStream* native_output = c2pa_open_file_stream("target/c_output_native.jpg", "w+b");
+    if (builder == NULL || native_input == NULL || native_output == NULL) {
+        printf("error opening native file streams = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign(&builder, key_signer, native_input, native_output);
+    if (err != 0) {
+        printf("error signing with native file streams = %s\n", c2pa_error());
+        return 1;
+    }
+    c2pa_release_stream(native_input);
+    c2pa_release_stream(native_output);
+    c2pa_release_manifest_builder(builder);
+    printf("manifest added to: %s\n", "target/c_output_native.jpg" );
+
+    // sign into a pipe, which can only be written to
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    C2paStream* pipe_input = c2pa_open_file_stream(asset_path, "rb");
+    C2paStream* pipe_output = open_pipe_stream("cat > target/c_output_pipe.jpg");
+    if (builder == NULL || pipe_input == NULL || pipe_output == NULL) {
+        printf("error opening pipe stream = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign_sequential(&builder, key_signer, pipe_input, pipe_output);
+    if (err != 0) {
+        printf("error signing into a pipe = %s\n", c2pa_error());
+        return 1;
+    }
+    c2pa_release_stream(pipe_input);
+    c2pa_release_stream(pipe_output);
+    c2pa_release_manifest_builder(builder);
+    printf("manifest added to: %s\n", "target/c_output_pipe.jpg" );
+
+    // sign an asset read from a pipe, spooled so that it can be seeked
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    C2paStream* spool_input = open_spool_pipe_stream("cat tests/fixtures/A.jpg", 64 * 1024);
+    C2paStream* spool_output = c2pa_open_file_stream("target/c_output_spool.jpg", "w+b");
+    if (builder == NULL || spool_input == NULL || spool_output == NULL) {
+        printf("error opening spool stream = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign(&builder, key_signer, spool_input, spool_output);
+    if (err != 0) {
+        printf("error signing from a pipe = %s\n", c2pa_error());
+        return 1;
+    }
+    c2pa_release_stream(spool_input);
+    c2pa_release_stream(spool_output);
+    c2pa_release_manifest_builder(builder);
+    printf("manifest added to: %s\n", "target/c_output_spool.jpg" );
+
+    // sign and verify in memory, with streams implemented in Rust
+    FILE *asset_file = fopen(asset_path, "rb");
+    fseek(asset_file, 0, SEEK_END);
+    long asset_size = ftell(asset_file);
+    fseek(asset_file, 0, SEEK_SET);
+    uint8_t *asset_data = malloc(asset_size);
+    fread(asset_data, 1, asset_size, asset_file);
+    fclose(asset_file);
+    builder = c2pa_create_manifest_builder(&settings, manifest_json);
+    C2paMemoryStream* memory_input = c2pa_create_memory_stream(asset_data, asset_size);
+    C2paMemoryStream* memory_output = c2pa_create_memory_stream(NULL, 0);
+    free(asset_data);
+    if (builder == NULL || memory_input == NULL || memory_output == NULL) {
+        printf("error creating memory streams = %s\n", c2pa_error());
+        return 1;
+    }
+    err = c2pa_manifest_builder_sign(&builder, key_signer, c2pa_memory_stream_as_stream(memory_input), c2pa_memory_stream_as_stream(memory_output));
+    if (err != 0) {
+        printf("error signing with memory streams = %s\n", c2pa_error());
+        return 1;
+    }
+    intptr_t signed_size = c2pa_memory_stream_data(memory_output, NULL, 0);
+    uint8_t *signed_data = malloc(signed_size);
+    c2pa_memory_stream_data(memory_output, signed_data, signed_size);
+    c2pa_release_memory_stream(memory_input);
+    c2pa_release_memory_stream(memory_output);
+    c2pa_release_manifest_builder(builder);
+
+    ManifestStoreReader* memory_reader = c2pa_manifest_reader_new();
+    C2paMemoryStream* signed_stream = c2pa_create_memory_stream(signed_data, signed_size);
+    free(signed_data);
+    char* memory_json = c2pa_manifest_reader_read(&memory_reader, "image/jpeg", c2pa_memory_stream_as_stream(signed_stream));
+    if (memory_json == NULL) {
+        printf("error reading from memory = %s\n", c2pa_error());
+        return 1;
+    }
+    c2pa_release_string(memory_json);
+    c2pa_release_memory_stream(signed_stream);
+    c2pa_release_manifest_reader(memory_reader);
+    printf("signed and verified %ld bytes in memory\n", (long)signed_size);
+
+    // share one signer between builders on several threads
+    pthread_t threads[4];
+    SignThreadArgs args[4];
+    for (int i = 0; i < 4; i++) {
+        args[i].signer = key_signer;
+        snprintf(args[i].output_path, sizeof(args[i].output_path), "target/c_output_thread_%d.jpg", i);
+        pthread_create(&threads[i], NULL, sign_thread, &args[i]);
+    }
+    for (int i = 0; i < 4; i++) {
+        pthread_join(threads[i], NULL);
+        if (args[i].result != 0) {
+            printf("error signing on thread %d\n", i);
+            return 1;
+        }
+    }
+    c2pa_release_signer(key_signer);
+    c2pa_release_signer(signer);
+    printf("signed on %d threads with a shared signer\n", 4);
+    return 0;
 }
\ No newline at end of file
@@ -11,6 +11,7 @@
 # specific language governing permissions and limitations under
 # each license.
 
+import io
 import json
 import os
 import sys
@@ -74,6 +75,15 @@ class C2paStream(c2pa.Stream):
     def flush_stream(self) -> None:
         self.stream.flush()
 
+    def stream_length(self):
+        # the length of a file is known without seeking, once its writes are flushed
+        try:
+            if self.stream.writable():
+                self.stream.flush()
+            return os.fstat(self.stream.fileno()).st_size
+        except (AttributeError, OSError, io.UnsupportedOperation):
+            return None
+
     # A shortcut method to open a C2paStream from a path/mode
     def open_file(path: str, mode: str) -> c2pa.Stream:
         return C2paStream(open(path, mode))
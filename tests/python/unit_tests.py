@@ -15,6 +15,7 @@ import unittest
 from unittest.mock import mock_open, patch
 import c2pa_api
 from c2pa_api import c2pa
+import io
 import os
 PROJECT_PATH = os.getcwd()
 
@@ -56,6 +57,103 @@ class TestManifestStoreReader(unittest.TestCase):
                 manifestStore = c2pa_api.ManifestStoreReader("badFormat",file)
                 json = manifestStore.read()
 
+class TestNativeStream(unittest.TestCase):
+
+    def test_read_file(self):
+        stream = c2pa_api.c2pa.NativeStream.open_file(testPath, "rb")
+        json = c2pa_api.c2pa.ManifestStoreReader().read_native_stream("image/jpeg", stream)
+        self.assertIn("C.jpg", json)
+
+    def test_read_memory(self):
+        with open(testPath, "rb") as file:
+            stream = c2pa_api.c2pa.NativeStream.from_bytes(file.read())
+        json = c2pa_api.c2pa.ManifestStoreReader().read_native_stream("image/jpeg", stream)
+        self.assertIn("C.jpg", json)
+        with self.assertRaises(c2pa_api.c2pa.StreamError.Other):
+            c2pa_api.c2pa.NativeStream.open_file(testPath, "rb").data()
+
+    def test_read_spool(self):
+        with open(testPath, "rb") as file:
+            stream = c2pa_api.c2pa.NativeStream.spool(c2pa_api.C2paStream(file), 1024)
+            json = c2pa_api.c2pa.ManifestStoreReader().read_native_stream("image/jpeg", stream)
+            self.assertIn("C.jpg", json)
+            self.assertTrue(stream.is_spooled_to_file())
+
+    def test_open_missing_file(self):
+        with self.assertRaises(c2pa_api.c2pa.StreamError.Io):
+            c2pa_api.c2pa.NativeStream.open_file("does_not_exist.jpg", "rb")
+
+# Counts the calls made to the flush_stream and stream_length methods of a stream
+class CountingStream(c2pa_api.C2paStream):
+    def __init__(self, stream):
+        super().__init__(stream)
+        self.flushes = 0
+        self.lengths = 0
+        self.end_seeks = 0
+
+    def seek_stream(self, pos: int, mode: c2pa.SeekMode) -> int:
+        if mode is c2pa.SeekMode.END:
+            self.end_seeks += 1
+        return super().seek_stream(pos, mode)
+
+    def flush_stream(self) -> None:
+        self.flushes += 1
+        super().flush_stream()
+
+    def stream_length(self):
+        self.lengths += 1
+        return super().stream_length()
+
+# A stream written before stream_length was added to the Stream interface
+class NoLengthStream(c2pa.Stream):
+    def __init__(self, stream):
+        self.stream = stream
+
+    def read_stream(self, length: int) -> bytes:
+        return self.stream.read(length)
+
+    def seek_stream(self, pos: int, mode: c2pa.SeekMode) -> int:
+        whence = {c2pa.SeekMode.START: 0, c2pa.SeekMode.CURRENT: 1, c2pa.SeekMode.END: 2}[mode]
+        return self.stream.seek(pos, whence)
+
+    def write_stream(self, data: bytes) -> int:
+        return self.stream.write(data)
+
+    def flush_stream(self) -> None:
+        self.stream.flush()
+
+class TestStreamCallbacks(unittest.TestCase):
+
+    def test_stream_length(self):
+        with open(testPath, "rb") as file:
+            stream = CountingStream(file)
+            json = c2pa.ManifestStoreReader().read_stream("image/jpeg", stream)
+            self.assertIn("C.jpg", json)
+            self.assertGreater(stream.lengths, 0)
+            self.assertEqual(stream.end_seeks, 0)
+
+    def test_stream_without_length(self):
+        with open(testPath, "rb") as file:
+            json = c2pa.ManifestStoreReader().read_stream("image/jpeg", NoLengthStream(file))
+            self.assertIn("C.jpg", json)
+
+    def test_flush_after_sign(self):
+        with open(os.path.join(PROJECT_PATH, "tests", "fixtures", "ps256.pub"), "rb") as file:
+            certs = file.read()
+        signer = c2pa_api.LocalSigner.from_settings(
+            lambda data: c2pa_api.sign_ps256(data, "tests/fixtures/ps256.pem"), "ps256", certs)
+        builder = c2pa_api.ManifestBuilder(
+            c2pa.ManifestBuilderSettings("python-generator", False),
+            signer,
+            {"title": "Flushed", "format": "image/jpeg"})
+        with open(testPath, "rb") as file:
+            output = CountingStream(io.BytesIO())
+            builder.sign_stream(signer, c2pa_api.C2paStream(file), output)
+        self.assertEqual(output.flushes, 1)
+        output.stream.seek(0)
+        json = c2pa.ManifestStoreReader().read_stream("image/jpeg", c2pa_api.C2paStream(output.stream))
+        self.assertIn("Flushed", json)
+
 class TestManifestBuilder(unittest.TestCase):
     # Define a manifest as a dictionary
     manifestDefinition = {